@@ -0,0 +1,574 @@
+use crate::{
+    buffer::*, color::*, debug::*, error::*, pass::*, pipeline::*, query::*, renderbuffer::*, shader::*, texture::*, uniform::*, *,
+};
+use glow::HasContext;
+use std::{any::Any, collections::HashSet, time::Duration};
+
+pub struct Features {
+    pub instancing: bool,
+    /// `PrimitiveType::Points`
+    pub points: bool,
+    /// `PrimitiveType::LineLoop` and `PrimitiveType::TriangleFan`
+    pub loop_and_fan: bool,
+    /// GPU time measurements with `begin_gpu_scope`
+    pub timer_queries: bool,
+    /// `QueryType::SamplesPassed`
+    pub sample_count_queries: bool,
+    /// `QueryType::PrimitivesGenerated`
+    pub primitive_queries: bool,
+    /// Different color write masks and blending for the color attachments, see `PipelineParams::attachments`
+    pub independent_blend: bool,
+    /// `StoreAction::Discard` is passed on to the driver
+    pub framebuffer_invalidation: bool,
+}
+
+impl Features {
+    pub fn supports_primitive(&self, primitive_type: PrimitiveType) -> bool {
+        match primitive_type {
+            PrimitiveType::Points => self.points,
+            PrimitiveType::LineLoop | PrimitiveType::TriangleFan => self.loop_and_fan,
+            _ => true,
+        }
+    }
+
+    pub(crate) fn check_primitive(&self, primitive_type: PrimitiveType) -> Result<(), GfxError> {
+        if !self.supports_primitive(primitive_type) {
+            return Err(GfxError::Unsupported(format!("{primitive_type:?} primitives")));
+        }
+        Ok(())
+    }
+
+    pub fn supports_query(&self, query_type: QueryType) -> bool {
+        match query_type {
+            QueryType::AnySamplesPassed => true,
+            QueryType::SamplesPassed => self.sample_count_queries,
+            QueryType::PrimitivesGenerated => self.primitive_queries,
+        }
+    }
+
+    pub(crate) fn check_attachments(&self, params: &PipelineParams) -> Result<(), GfxError> {
+        if !self.independent_blend && !params.uniform_attachments() {
+            return Err(GfxError::Unsupported(
+                "different blending or color masks for color attachments".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_query(&self, query_type: QueryType) -> Result<(), GfxError> {
+        if !self.supports_query(query_type) {
+            return Err(GfxError::Unsupported(format!("{query_type:?} queries")));
+        }
+        Ok(())
+    }
+}
+
+/// Limits of the device, queried once when the backend is created
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Largest width and height of a texture
+    pub max_texture_size: u32,
+    /// Images a single shader may sample from
+    pub max_texture_units: usize,
+    /// Attribute slots of a pipeline, a `Mat4` attribute takes 4 of them
+    pub max_vertex_attributes: usize,
+    /// Most samples of a multisampled render target
+    pub max_samples: u32,
+    pub max_color_attachments: usize,
+    /// GLSL version in the form used by `#version`, e.g. 330 or 300.
+    /// `None` for backends that do not run GLSL.
+    pub glsl_version: Option<u32>,
+    pub extensions: HashSet<String>,
+}
+
+impl Capabilities {
+    pub(crate) fn from_gl(gl: &glow::Context) -> Self {
+        let get = |parameter| unsafe { gl.get_parameter_i32(parameter) }.max(0);
+        let glsl_version = unsafe { gl.get_parameter_string(glow::SHADING_LANGUAGE_VERSION) };
+
+        Self {
+            max_texture_size: get(glow::MAX_TEXTURE_SIZE) as u32,
+            max_texture_units: get(glow::MAX_TEXTURE_IMAGE_UNITS) as usize,
+            max_vertex_attributes: get(glow::MAX_VERTEX_ATTRIBS) as usize,
+            max_samples: get(glow::MAX_SAMPLES) as u32,
+            max_color_attachments: get(glow::MAX_COLOR_ATTACHMENTS) as usize,
+            glsl_version: parse_glsl_version(&glsl_version),
+            extensions: gl.supported_extensions().clone(),
+        }
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.contains(name)
+    }
+
+    pub(crate) fn check_texture_size(&self, width: u32, height: u32) -> Result<(), GfxError> {
+        if width > self.max_texture_size || height > self.max_texture_size {
+            return Err(GfxError::Unsupported(format!(
+                "{width}x{height} texture, the maximum size is {}",
+                self.max_texture_size
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_color_attachments(&self, count: usize) -> Result<(), GfxError> {
+        let max = self.max_color_attachments.min(MAX_COLOR_ATTACHMENTS);
+        if count == 0 {
+            return Err(GfxError::LayoutMismatch("render pass without color attachments".to_string()));
+        }
+        if count > max {
+            return Err(GfxError::Unsupported(format!(
+                "render pass with {count} color attachments, at most {max} are supported"
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_samples(&self, samples: u32) -> Result<(), GfxError> {
+        if samples > 1 && samples > self.max_samples {
+            return Err(GfxError::Unsupported(format!(
+                "{samples} samples per pixel, at most {} are supported",
+                self.max_samples
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_images(&self, images: usize) -> Result<(), GfxError> {
+        if images > self.max_texture_units {
+            return Err(GfxError::Unsupported(format!(
+                "shader with {images} images, at most {} are supported",
+                self.max_texture_units
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_attributes(&self, attributes: &[VertexAttribute]) -> Result<(), GfxError> {
+        let slots: usize = attributes.iter().map(|attribute| attribute.format.slots()).sum();
+        if slots > self.max_vertex_attributes {
+            return Err(GfxError::Unsupported(format!(
+                "pipeline with {slots} attribute slots, at most {} are supported",
+                self.max_vertex_attributes
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// `#version` number from a `GL_SHADING_LANGUAGE_VERSION` string,
+/// e.g. "4.60 NVIDIA" or "OpenGL ES GLSL ES 3.00"
+fn parse_glsl_version(version: &str) -> Option<u32> {
+    let number = version
+        .split_whitespace()
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))?;
+    let (major, minor) = number.split_once('.')?;
+    let minor = &minor[..minor.find(|c: char| !c.is_ascii_digit()).unwrap_or(minor.len())];
+    // "4.6" is 460, the same as "4.60"
+    let minor = match minor.len() {
+        0 => return None,
+        1 => minor.parse::<u32>().ok()? * 10,
+        _ => minor[..2].parse::<u32>().ok()?,
+    };
+    Some(major.parse::<u32>().ok()? * 100 + minor)
+}
+
+/// Counters of a single frame, between two `commit_frame` calls
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    pub draw_calls: u32,
+    /// Instances drawn by all of the draw calls
+    pub instances: u32,
+    /// Vertices or indices submitted by all of the draw calls, counted once per instance
+    pub vertices: u64,
+    /// `apply_pipeline` calls with a pipeline other than the current one
+    pub pipeline_switches: u32,
+    pub texture_binds: u32,
+    /// Buffer binds skipped because the buffer was already bound
+    pub buffer_binds_skipped: u32,
+    pub uniform_uploads: u32,
+    /// Bytes uploaded with `buffer_update` and `texture_update_part`
+    pub bytes_uploaded: u64,
+}
+
+impl FrameStats {
+    pub(crate) fn count_draw(&mut self, count: i32, instance_count: i32) {
+        self.draw_calls += 1;
+        self.instances += instance_count as u32;
+        self.vertices += count as u64 * instance_count as u64;
+    }
+}
+
+/// GPU time of a scope measured with `begin_gpu_scope`
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuTiming {
+    pub name: String,
+    /// Rolling average of the last results
+    pub average: Duration,
+    pub last: Duration,
+}
+
+/// Resource, pass and draw API implemented by every rendering backend.
+///
+/// `QuadContext` dereferences to the backend it was created with, so all of
+/// those methods are available directly on the context.
+pub trait RenderingBackend {
+    /// Used by `QuadContext::backend` to get back the concrete backend type
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn features(&self) -> &Features;
+
+    fn capabilities(&self) -> &Capabilities;
+
+    /// Size of the default framebuffer
+    fn resize(&mut self, width: i32, height: i32);
+
+    /// Route the driver debug messages to `callback`.
+    /// Returns `false` if the backend has no debug output. GL needs `KHR_debug`,
+    /// or `GL_ARB_debug_output` with a backend created by `GlBackend::from_loader`.
+    ///
+    /// There is a single callback for the whole process, enabling the debug output
+    /// again replaces the callback set before.
+    fn enable_debug_output(&mut self, _callback: DebugCallback) -> bool {
+        false
+    }
+
+    /// Name `resource` in the driver debug messages and graphics debuggers, `None` removes the label.
+    /// Backends without debug labels only validate the handle.
+    fn set_label(&mut self, resource: Resource, label: Option<&str>) -> Result<(), GfxError>;
+
+    fn new_shader(&mut self, shader: ShaderSource, meta: ShaderMeta) -> Result<ShaderId, GfxError>;
+
+    /// `new_shader` named `label` in the driver debug messages, see `set_label`
+    fn new_shader_with_label(&mut self, shader: ShaderSource, meta: ShaderMeta, label: Option<&str>) -> Result<ShaderId, GfxError> {
+        let shader = self.new_shader(shader, meta)?;
+        if label.is_some() {
+            self.set_label(shader.into(), label)?;
+        }
+        Ok(shader)
+    }
+
+    /// Delete shader program. Pipelines created with this shader are left alive,
+    /// but `apply_pipeline` will refuse them with `GfxError::InvalidHandle`.
+    fn delete_shader(&mut self, shader: ShaderId) -> Result<(), GfxError>;
+
+    /// Create a texture, `bytes` should hold the whole image in `params.format`,
+    /// one after the other for each face of a cube map or layer of an array texture
+    fn new_texture(&mut self, access: TextureAccess, bytes: Option<&[u8]>, params: TextureParams) -> Result<TextureId, GfxError>;
+
+    /// `new_texture` named `label` in the driver debug messages, see `set_label`
+    fn new_texture_with_label(
+        &mut self,
+        access: TextureAccess,
+        bytes: Option<&[u8]>,
+        params: TextureParams,
+        label: Option<&str>,
+    ) -> Result<TextureId, GfxError> {
+        let texture = self.new_texture(access, bytes, params)?;
+        if label.is_some() {
+            self.set_label(texture.into(), label)?;
+        }
+        Ok(texture)
+    }
+
+    fn new_texture_from_rgba8(&mut self, width: u16, height: u16, bytes: &[u8]) -> Result<TextureId, GfxError> {
+        self.new_texture(
+            TextureAccess::Static,
+            Some(bytes),
+            TextureParams {
+                width: width as _,
+                height: height as _,
+                format: TextureFormat::RGBA8,
+                wrap: TextureWrap::Clamp,
+                filter: FilterMode::Nearest,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Delete texture. The slot of the texture will be reused by the following
+    /// `new_texture` calls, using `texture` afterwards results in `GfxError::InvalidHandle`.
+    fn delete_texture(&mut self, texture: TextureId) -> Result<(), GfxError>;
+
+    fn texture_params(&self, texture: TextureId) -> Result<TextureParams, GfxError>;
+
+    fn texture_size(&self, texture: TextureId) -> Result<(u32, u32), GfxError> {
+        let params = self.texture_params(texture)?;
+        Ok((params.width, params.height))
+    }
+
+    fn texture_set_filter(&mut self, texture: TextureId, filter: FilterMode) -> Result<(), GfxError>;
+
+    fn texture_set_wrap(&mut self, texture: TextureId, wrap: TextureWrap) -> Result<(), GfxError>;
+
+    fn texture_resize(&mut self, texture: TextureId, width: u32, height: u32, bytes: Option<&[u8]>) -> Result<(), GfxError>;
+
+    /// Read texture data into CPU memory
+    fn texture_read_pixels(&mut self, texture: TextureId, bytes: &mut [u8]) -> Result<(), GfxError>;
+
+    /// Update whole texture content
+    /// bytes should be width * height * 4 size - non rgba8 textures are not supported yet anyway
+    fn texture_update(&mut self, texture: TextureId, bytes: &[u8]) -> Result<(), GfxError> {
+        let (width, height) = self.texture_size(texture)?;
+        self.texture_update_part(texture, 0 as _, 0 as _, width as _, height as _, bytes)
+    }
+
+    fn texture_update_part(
+        &mut self,
+        texture: TextureId,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        bytes: &[u8],
+    ) -> Result<(), GfxError>;
+
+    /// Storage for a render pass attachment that is never sampled, multisampled for more than 1 `samples`
+    fn new_renderbuffer(&mut self, format: TextureFormat, width: u32, height: u32, samples: u32) -> Result<RenderbufferId, GfxError>;
+
+    fn renderbuffer_params(&self, renderbuffer: RenderbufferId) -> Result<RenderbufferParams, GfxError>;
+
+    fn delete_renderbuffer(&mut self, renderbuffer: RenderbufferId) -> Result<(), GfxError>;
+
+    fn new_render_pass(&mut self, color_img: TextureId, depth_img: Option<TextureId>) -> Result<RenderPass, GfxError> {
+        self.new_render_pass_mrt(&[color_img], depth_img)
+    }
+
+    /// Render pass with several color attachments, output `n` of the fragment shader is written to `color_imgs[n]`.
+    /// All of the attachments must have the same size.
+    fn new_render_pass_mrt(&mut self, color_imgs: &[TextureId], depth_img: Option<TextureId>) -> Result<RenderPass, GfxError> {
+        self.new_render_pass_msaa(color_imgs, depth_img, 1)
+    }
+
+    /// Multisampled render pass. Draws go to renderbuffers with `samples` samples per pixel,
+    /// `end_render_pass` resolves them into `color_imgs` and `depth_img`.
+    /// `samples` of 1 renders straight into the textures, the same as `new_render_pass_mrt`.
+    fn new_render_pass_msaa(
+        &mut self,
+        color_imgs: &[TextureId],
+        depth_img: Option<TextureId>,
+        samples: u32,
+    ) -> Result<RenderPass, GfxError> {
+        let color: Vec<_> = color_imgs.iter().copied().map(Attachment::from).collect();
+        self.new_render_pass_with_attachments(&color, depth_img.map(Attachment::from), samples)
+    }
+
+    /// Render pass with texture or renderbuffer attachments.
+    /// Renderbuffer attachments must have `samples` samples, texture attachments
+    /// of a multisampled pass are resolved by `end_render_pass` as in `new_render_pass_msaa`.
+    /// `Attachment::TextureLevel` renders to a mip level, a cube map face or an array layer,
+    /// all of the attachments must have the same size.
+    /// Combinations of attachments the driver can not render to are reported as errors.
+    fn new_render_pass_with_attachments(
+        &mut self,
+        color: &[Attachment],
+        depth: Option<Attachment>,
+        samples: u32,
+    ) -> Result<RenderPass, GfxError>;
+
+    /// `new_render_pass_with_attachments` named `label` in the driver debug messages, see `set_label`
+    fn new_render_pass_with_label(
+        &mut self,
+        color: &[Attachment],
+        depth: Option<Attachment>,
+        samples: u32,
+        label: Option<&str>,
+    ) -> Result<RenderPass, GfxError> {
+        let pass = self.new_render_pass_with_attachments(color, depth, samples)?;
+        if label.is_some() {
+            self.set_label(pass.into(), label)?;
+        }
+        Ok(pass)
+    }
+
+    /// The first color attachment of the pass, `GfxError::InvalidState` if it is a renderbuffer
+    fn render_pass_texture(&self, pass: RenderPass) -> Result<TextureId, GfxError> {
+        self.render_pass_color_attachments(pass)?[0]
+            .texture()
+            .ok_or_else(|| GfxError::InvalidState("the first color attachment of the pass is a renderbuffer".to_string()))
+    }
+
+    fn render_pass_color_attachments(&self, pass: RenderPass) -> Result<&[Attachment], GfxError>;
+
+    /// Delete the render pass together with its attachments.
+    /// Attachments that were already deleted with `delete_texture` or `delete_renderbuffer` are skipped.
+    fn delete_render_pass(&mut self, pass: RenderPass) -> Result<(), GfxError>;
+
+    fn new_pipeline(
+        &mut self,
+        buffer_layout: &[BufferLayout],
+        attributes: &[VertexAttribute],
+        shader: ShaderId,
+    ) -> Result<Pipeline, GfxError> {
+        self.new_pipeline_with_params(buffer_layout, attributes, shader, Default::default())
+    }
+
+    fn new_pipeline_with_params(
+        &mut self,
+        buffer_layout: &[BufferLayout],
+        attributes: &[VertexAttribute],
+        shader: ShaderId,
+        params: PipelineParams,
+    ) -> Result<Pipeline, GfxError>;
+
+    /// `new_pipeline_with_params` named `label` in the driver debug messages, see `set_label`
+    fn new_pipeline_with_label(
+        &mut self,
+        buffer_layout: &[BufferLayout],
+        attributes: &[VertexAttribute],
+        shader: ShaderId,
+        params: PipelineParams,
+        label: Option<&str>,
+    ) -> Result<Pipeline, GfxError> {
+        let pipeline = self.new_pipeline_with_params(buffer_layout, attributes, shader, params)?;
+        if label.is_some() {
+            self.set_label(pipeline.into(), label)?;
+        }
+        Ok(pipeline)
+    }
+
+    fn delete_pipeline(&mut self, pipeline: Pipeline) -> Result<(), GfxError>;
+
+    fn apply_pipeline(&mut self, pipeline: &Pipeline) -> Result<(), GfxError>;
+
+    fn new_buffer(&mut self, type_: BufferType, usage: BufferUsage, data: BufferSource) -> Result<BufferId, GfxError>;
+
+    /// `new_buffer` named `label` in the driver debug messages, see `set_label`
+    fn new_buffer_with_label(
+        &mut self,
+        type_: BufferType,
+        usage: BufferUsage,
+        data: BufferSource,
+        label: Option<&str>,
+    ) -> Result<BufferId, GfxError> {
+        let buffer = self.new_buffer(type_, usage, data)?;
+        if label.is_some() {
+            self.set_label(buffer.into(), label)?;
+        }
+        Ok(buffer)
+    }
+
+    /// Update the beginning of the buffer with `data`, which should not be larger than the buffer.
+    /// Index buffers should be updated with indices of the same size they were created with.
+    fn buffer_update(&mut self, buffer: BufferId, data: BufferSource) -> Result<(), GfxError>;
+
+    /// Size of buffer in bytes
+    fn buffer_size(&mut self, buffer: BufferId) -> Result<usize, GfxError>;
+
+    /// Delete buffer. The slot of the buffer will be reused by the following
+    /// `new_buffer` calls, using `buffer` afterwards results in `GfxError::InvalidHandle`.
+    fn delete_buffer(&mut self, buffer: BufferId) -> Result<(), GfxError>;
+
+    /// Set a new viewport rectangle.
+    /// Should be applied after begin_pass.
+    fn apply_viewport(&mut self, x: i32, y: i32, w: i32, h: i32);
+
+    /// Set a new scissor rectangle.
+    /// Should be applied after begin_pass.
+    fn apply_scissor_rect(&mut self, x: i32, y: i32, w: i32, h: i32);
+
+    fn apply_bindings(&mut self, bindings: &Bindings) -> Result<(), GfxError>;
+
+    fn apply_uniforms(&mut self, uniforms: UniformsSource) -> Result<(), GfxError> {
+        self.apply_uniforms_from_bytes(uniforms.0.as_slice(), uniforms.0.size)
+    }
+
+    fn apply_uniforms_from_bytes(&mut self, uniforms: &[u8], size: usize) -> Result<(), GfxError>;
+
+    /// Clear the current pass, the color applies to every color attachment.
+    /// Integer attachments are left undefined, clear them with `clear_attachment`.
+    fn clear(&mut self, color: Option<Color>, depth: Option<f32>, stencil: Option<i32>);
+
+    /// Clear color attachment `index` of the current pass to the exact `value`,
+    /// within the scissor rectangle and the color write mask of the attachment.
+    /// Returns `LayoutMismatch` if there is no such attachment or `value` does not match its format,
+    /// the default pass has a single `Float` attachment.
+    fn clear_attachment(&mut self, index: usize, value: ClearValue) -> Result<(), GfxError>;
+
+    /// start rendering to the default frame buffer
+    fn begin_default_pass(&mut self, action: PassAction) {
+        self.begin_default_pass_with_store(action, StoreActions::default())
+    }
+
+    /// `begin_default_pass` with the store actions applied by `end_render_pass`
+    fn begin_default_pass_with_store(&mut self, action: PassAction, store: StoreActions);
+
+    /// start rendering to an offscreen framebuffer
+    fn begin_pass(&mut self, pass: RenderPass, action: PassAction) -> Result<(), GfxError> {
+        self.begin_pass_with_store(pass, action, StoreActions::default())
+    }
+
+    /// `begin_pass` with the store actions applied by `end_render_pass`.
+    /// Discarded attachments of a multisampled pass are not resolved.
+    /// Without `Features::framebuffer_invalidation` the contents are kept, which is a valid outcome of a discard.
+    fn begin_pass_with_store(&mut self, pass: RenderPass, action: PassAction, store: StoreActions) -> Result<(), GfxError>;
+
+    fn end_render_pass(&mut self);
+
+    fn commit_frame(&mut self);
+
+    /// Counters of the last frame finished with `commit_frame`.
+    /// Counters not tracked by the backend are left at zero.
+    fn frame_stats(&self) -> FrameStats {
+        FrameStats::default()
+    }
+
+    /// Start measuring the GPU time of the following commands under `name`.
+    /// Scopes can not be nested, beginning a scope ends the current one.
+    /// Does nothing if `Features::timer_queries` is not supported.
+    fn begin_gpu_scope(&mut self, _name: &str) {}
+
+    fn end_gpu_scope(&mut self) {}
+
+    /// GPU time of every scope measured so far.
+    /// Results are collected without stalling the GPU, so they lag a few frames behind.
+    fn gpu_timings(&self) -> Vec<GpuTiming> {
+        vec![]
+    }
+
+    /// Create a query, `GfxError::Unsupported` if `query_type` is not in the `Features`
+    fn new_query(&mut self, query_type: QueryType) -> Result<QueryId, GfxError>;
+
+    /// Delete query, a query that is still active is ended first
+    fn delete_query(&mut self, query: QueryId) -> Result<(), GfxError>;
+
+    /// Start counting the draws that follow, only one query of every `QueryType` may be active at a time.
+    /// Beginning a query again discards its previous result.
+    fn begin_query(&mut self, query: QueryId) -> Result<(), GfxError>;
+
+    fn end_query(&mut self, query: QueryId) -> Result<(), GfxError>;
+
+    /// Result of the last `begin_query`/`end_query` pair, `None` while the GPU is still working on it.
+    /// Never blocks, poll it once per frame until it is available.
+    fn query_result(&mut self, query: QueryId) -> Result<Option<u64>, GfxError>;
+
+    fn draw(&mut self, first: i32, count: i32, instance_count: i32);
+
+    /// Draw `count` indices of the index buffer from the current bindings,
+    /// starting at `first_index`.
+    fn draw_indexed(&mut self, first_index: i32, count: i32, instance_count: i32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glsl_version_strings() {
+        assert_eq!(parse_glsl_version("4.60 NVIDIA"), Some(460));
+        assert_eq!(parse_glsl_version("4.60.0 - Build 31.0.101.2111"), Some(460));
+        assert_eq!(parse_glsl_version("1.50"), Some(150));
+        assert_eq!(parse_glsl_version("4.6"), Some(460));
+        assert_eq!(parse_glsl_version("OpenGL ES GLSL ES 3.00"), Some(300));
+        assert_eq!(parse_glsl_version("WebGL GLSL ES 3.00 (OpenGL ES GLSL ES 3.0 Chromium)"), Some(300));
+    }
+
+    #[test]
+    fn glsl_version_garbage() {
+        assert_eq!(parse_glsl_version(""), None);
+        assert_eq!(parse_glsl_version("OpenGL ES GLSL ES"), None);
+        assert_eq!(parse_glsl_version("4"), None);
+        assert_eq!(parse_glsl_version("4.x"), None);
+    }
+}
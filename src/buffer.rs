@@ -1,3 +1,5 @@
+use crate::error::{GfxError, ResourceKind};
+use crate::resource::{resource_id, Handle};
 use std::mem::size_of;
 
 #[derive(Clone, Copy, Debug)]
@@ -17,6 +19,20 @@ pub enum BufferType {
     IndexBuffer = glow::ELEMENT_ARRAY_BUFFER as _,
 }
 
+impl BufferType {
+    /// Dimension of the indices of a buffer of this type with `element_size` elements,
+    /// `None` for vertex buffers
+    pub(crate) fn index_type(self, element_size: usize) -> Result<Option<u32>, GfxError> {
+        match self {
+            BufferType::VertexBuffer => Ok(None),
+            BufferType::IndexBuffer if matches!(element_size, 1 | 2 | 4) => Ok(Some(element_size as u32)),
+            BufferType::IndexBuffer => Err(GfxError::Unsupported(format!(
+                "index buffer with {element_size} byte indices, only u8, u16 and u32 are supported"
+            ))),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BufferUsage {
     Immutable = glow::STATIC_DRAW as _,
@@ -24,8 +40,9 @@ pub enum BufferUsage {
     Stream = glow::STREAM_DRAW as _,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct BufferId(pub(crate) usize);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferId(pub(crate) Handle);
+resource_id!(BufferId, Buffer);
 
 /// A vtable-erased generic argument.
 /// Basically, the same thing as `fn f<U>(a: &U)`, but
@@ -65,6 +82,34 @@ impl<'a> BufferSource<'a> {
         }
     }
 
+    /// Size in bytes and element size of the source
+    pub(crate) fn size(&self) -> (usize, usize) {
+        match self {
+            BufferSource::Slice(data) => (data.size, data.element_size),
+            BufferSource::Empty { size, element_size } => (*size, *element_size),
+        }
+    }
+
+    /// Data for `buffer_update` of a buffer of `size` bytes with `index_type` indices
+    pub(crate) fn update_data(self, size: usize, index_type: Option<u32>) -> Result<Arg<'a>, GfxError> {
+        let data = match self {
+            BufferSource::Slice(data) => data,
+            BufferSource::Empty { .. } => return Err(GfxError::LayoutMismatch("buffer_update expects BufferSource::slice".to_string())),
+        };
+        if let Some(index_type) = index_type {
+            if data.element_size != index_type as usize {
+                return Err(GfxError::LayoutMismatch(format!(
+                    "index buffer of {index_type} byte indices updated with {} byte elements",
+                    data.element_size
+                )));
+            }
+        }
+        if data.size > size {
+            return Err(GfxError::OutOfBounds(ResourceKind::Buffer));
+        }
+        Ok(data)
+    }
+
     pub fn slice<T>(data: &'a [T]) -> BufferSource<'a> {
         BufferSource::Slice(Arg {
             ptr: data.as_ptr() as _,
@@ -1,8 +1,7 @@
-use crate::MAX_SHADERSTAGE_IMAGES;
-use crate::MAX_VERTEX_ATTRIBUTES;
 use crate::{
-    pipeline::{BlendState, CullFace, Pipeline, StencilState},
-    ColorMask,
+    backend::FrameStats,
+    color::Color,
+    pipeline::{AttachmentParams, Comparison, CullFace, Pipeline, StencilState, MAX_COLOR_ATTACHMENTS},
 };
 use glow::HasContext;
 
@@ -27,18 +26,26 @@ pub struct GlCache {
     pub stored_index_buffer: Option<glow::Buffer>,
     pub stored_index_type: Option<u32>,
     pub stored_vertex_buffer: Option<glow::Buffer>,
-    pub stored_texture: Option<glow::Texture>,
+    pub stored_texture: (u32, Option<glow::Texture>),
     pub index_buffer: Option<glow::Buffer>,
     pub index_type: Option<u32>,
     pub vertex_buffer: Option<glow::Buffer>,
-    pub textures: [Option<glow::Texture>; MAX_SHADERSTAGE_IMAGES],
+    /// Target and texture bound to every texture unit, sized from `Capabilities::max_texture_units`
+    pub textures: Vec<(u32, Option<glow::Texture>)>,
     pub cur_pipeline: Option<Pipeline>,
-    pub color_blend: Option<BlendState>,
-    pub alpha_blend: Option<BlendState>,
+    /// Blending and color write mask of every color attachment
+    pub attachments: [AttachmentParams; MAX_COLOR_ATTACHMENTS],
+    pub blend_color: Color,
     pub stencil: Option<StencilState>,
-    pub color_write: ColorMask,
     pub cull_face: CullFace,
-    pub attributes: [Option<CachedAttribute>; MAX_VERTEX_ATTRIBUTES],
+    /// Depth comparison, `None` while `DEPTH_TEST` is disabled
+    pub depth_test: Option<Comparison>,
+    pub depth_write: bool,
+    pub polygon_offset: Option<(f32, f32)>,
+    /// Sized from `Capabilities::max_vertex_attributes`
+    pub attributes: Vec<Option<CachedAttribute>>,
+    /// Counters of the frame in progress
+    pub stats: FrameStats,
 }
 
 impl GlCache {
@@ -47,11 +54,15 @@ impl GlCache {
             if self.vertex_buffer != buffer {
                 self.vertex_buffer = buffer;
                 unsafe { gl.bind_buffer(target, buffer) }
+            } else {
+                self.stats.buffer_binds_skipped += 1;
             }
         } else {
             if self.index_buffer != buffer {
                 self.index_buffer = buffer;
                 unsafe { gl.bind_buffer(target, buffer) }
+            } else {
+                self.stats.buffer_binds_skipped += 1;
             }
             self.index_type = index_type;
         }
@@ -78,12 +89,18 @@ impl GlCache {
         }
     }
 
-    pub fn bind_texture(&mut self, gl: &glow::Context, slot_index: usize, texture: Option<glow::Texture>) {
+    pub fn bind_texture(&mut self, gl: &glow::Context, slot_index: usize, target: u32, texture: Option<glow::Texture>) {
         unsafe {
             gl.active_texture(glow::TEXTURE0 + slot_index as u32);
-            if self.textures[slot_index] != texture {
-                gl.bind_texture(glow::TEXTURE_2D, texture);
-                self.textures[slot_index] = texture;
+            let (bound_target, bound_texture) = self.textures[slot_index];
+            if (bound_target, bound_texture) != (target, texture) {
+                // every target of a unit has its own binding, the previous texture would stay bound to its target
+                if bound_target != target && bound_texture.is_some() {
+                    gl.bind_texture(bound_target, None);
+                }
+                gl.bind_texture(target, texture);
+                self.textures[slot_index] = (target, texture);
+                self.stats.texture_binds += 1;
             }
         }
     }
@@ -93,7 +110,8 @@ impl GlCache {
     }
 
     pub fn restore_texture_binding(&mut self, gl: &glow::Context, slot_index: usize) {
-        self.bind_texture(gl, slot_index, self.stored_texture);
+        let (target, texture) = self.stored_texture;
+        self.bind_texture(gl, slot_index, target, texture);
     }
 
     pub fn clear_buffer_bindings(&mut self, gl: &glow::Context) {
@@ -105,17 +123,16 @@ impl GlCache {
     }
 
     pub fn clear_texture_bindings(&mut self, gl: &glow::Context) {
-        for ix in 0..MAX_SHADERSTAGE_IMAGES {
-            if self.textures[ix].is_some() {
-                self.bind_texture(gl, ix, None);
-                self.textures[ix] = None;
+        for ix in 0..self.textures.len() {
+            let (target, texture) = self.textures[ix];
+            if texture.is_some() {
+                self.bind_texture(gl, ix, target, None);
             }
         }
     }
 
     pub fn clear_vertex_attributes(&mut self) {
-        for attr_index in 0..MAX_VERTEX_ATTRIBUTES {
-            let cached_attr = &mut self.attributes[attr_index];
+        for cached_attr in &mut self.attributes {
             *cached_attr = None;
         }
     }
@@ -0,0 +1,203 @@
+use crate::{
+    backend::RenderingBackend, color::Color, error::GfxError, pass::*, pipeline::Pipeline, query::QueryId, uniform::UniformsSource,
+    Bindings,
+};
+
+/// Single recorded call of `CommandBuffer`, replayed as the `RenderingBackend` method of the same name
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    BeginDefaultPass(PassAction, StoreActions),
+    BeginPass(RenderPass, PassAction, StoreActions),
+    EndRenderPass,
+    ApplyPipeline(Pipeline),
+    ApplyBindings(Bindings),
+    /// Owned copy of the uniform data
+    ApplyUniforms(Vec<u8>),
+    ApplyViewport {
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+    },
+    ApplyScissorRect {
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+    },
+    Clear {
+        color: Option<Color>,
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    },
+    ClearAttachment {
+        index: usize,
+        value: ClearValue,
+    },
+    Draw {
+        first: i32,
+        count: i32,
+        instance_count: i32,
+    },
+    DrawIndexed {
+        first_index: i32,
+        count: i32,
+        instance_count: i32,
+    },
+    BeginGpuScope(String),
+    EndGpuScope,
+    BeginQuery(QueryId),
+    EndQuery(QueryId),
+}
+
+/// List of passes, state applications and draws, recorded without access to the context.
+///
+/// `CommandBuffer` owns everything it records, so it may be built on any thread,
+/// sent to the thread owning the `QuadContext` and replayed there with `QuadContext::submit`.
+/// Handles are only validated on submit.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandBuffer {
+    commands: Vec<Command>,
+}
+
+impl CommandBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Remove all of the recorded commands, keeping the allocation for the next frame
+    pub fn clear_commands(&mut self) {
+        self.commands.clear();
+    }
+
+    pub fn begin_default_pass(&mut self, action: PassAction) {
+        self.begin_default_pass_with_store(action, StoreActions::default());
+    }
+
+    pub fn begin_default_pass_with_store(&mut self, action: PassAction, store: StoreActions) {
+        self.commands.push(Command::BeginDefaultPass(action, store));
+    }
+
+    pub fn begin_pass(&mut self, pass: RenderPass, action: PassAction) {
+        self.begin_pass_with_store(pass, action, StoreActions::default());
+    }
+
+    pub fn begin_pass_with_store(&mut self, pass: RenderPass, action: PassAction, store: StoreActions) {
+        self.commands.push(Command::BeginPass(pass, action, store));
+    }
+
+    pub fn end_render_pass(&mut self) {
+        self.commands.push(Command::EndRenderPass);
+    }
+
+    pub fn apply_pipeline(&mut self, pipeline: &Pipeline) {
+        self.commands.push(Command::ApplyPipeline(*pipeline));
+    }
+
+    pub fn apply_bindings(&mut self, bindings: &Bindings) {
+        self.commands.push(Command::ApplyBindings(bindings.clone()));
+    }
+
+    /// Copy the uniform data into the buffer, `uniforms` may be dropped right after the call
+    pub fn apply_uniforms(&mut self, uniforms: UniformsSource) {
+        self.apply_uniforms_from_bytes(uniforms.0.as_slice(), uniforms.0.size)
+    }
+
+    /// `size` past the end of `uniforms` is clamped to it, the backend checks the size on replay
+    pub fn apply_uniforms_from_bytes(&mut self, uniforms: &[u8], size: usize) {
+        self.commands
+            .push(Command::ApplyUniforms(uniforms[..size.min(uniforms.len())].to_vec()));
+    }
+
+    pub fn apply_viewport(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.commands.push(Command::ApplyViewport { x, y, w, h });
+    }
+
+    pub fn apply_scissor_rect(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.commands.push(Command::ApplyScissorRect { x, y, w, h });
+    }
+
+    pub fn clear(&mut self, color: Option<Color>, depth: Option<f32>, stencil: Option<i32>) {
+        self.commands.push(Command::Clear { color, depth, stencil });
+    }
+
+    pub fn clear_attachment(&mut self, index: usize, value: impl Into<ClearValue>) {
+        self.commands.push(Command::ClearAttachment {
+            index,
+            value: value.into(),
+        });
+    }
+
+    pub fn draw(&mut self, first: i32, count: i32, instance_count: i32) {
+        self.commands.push(Command::Draw {
+            first,
+            count,
+            instance_count,
+        });
+    }
+
+    pub fn draw_indexed(&mut self, first_index: i32, count: i32, instance_count: i32) {
+        self.commands.push(Command::DrawIndexed {
+            first_index,
+            count,
+            instance_count,
+        });
+    }
+
+    pub fn begin_gpu_scope(&mut self, name: &str) {
+        self.commands.push(Command::BeginGpuScope(name.to_string()));
+    }
+
+    pub fn end_gpu_scope(&mut self) {
+        self.commands.push(Command::EndGpuScope);
+    }
+
+    pub fn begin_query(&mut self, query: QueryId) {
+        self.commands.push(Command::BeginQuery(query));
+    }
+
+    pub fn end_query(&mut self, query: QueryId) {
+        self.commands.push(Command::EndQuery(query));
+    }
+
+    /// Replay the commands on `backend`, stopping at the first invalid handle
+    pub(crate) fn replay(&self, backend: &mut dyn RenderingBackend) -> Result<(), GfxError> {
+        for command in &self.commands {
+            match command {
+                Command::BeginDefaultPass(action, store) => backend.begin_default_pass_with_store(*action, *store),
+                Command::BeginPass(pass, action, store) => backend.begin_pass_with_store(*pass, *action, *store)?,
+                Command::EndRenderPass => backend.end_render_pass(),
+                Command::ApplyPipeline(pipeline) => backend.apply_pipeline(pipeline)?,
+                Command::ApplyBindings(bindings) => backend.apply_bindings(bindings)?,
+                Command::ApplyUniforms(bytes) => backend.apply_uniforms_from_bytes(bytes, bytes.len())?,
+                Command::ApplyViewport { x, y, w, h } => backend.apply_viewport(*x, *y, *w, *h),
+                Command::ApplyScissorRect { x, y, w, h } => backend.apply_scissor_rect(*x, *y, *w, *h),
+                Command::Clear { color, depth, stencil } => backend.clear(*color, *depth, *stencil),
+                Command::ClearAttachment { index, value } => backend.clear_attachment(*index, *value)?,
+                Command::Draw {
+                    first,
+                    count,
+                    instance_count,
+                } => backend.draw(*first, *count, *instance_count),
+                Command::DrawIndexed {
+                    first_index,
+                    count,
+                    instance_count,
+                } => backend.draw_indexed(*first_index, *count, *instance_count),
+                Command::BeginGpuScope(name) => backend.begin_gpu_scope(name),
+                Command::EndGpuScope => backend.end_gpu_scope(),
+                Command::BeginQuery(query) => backend.begin_query(*query)?,
+                Command::EndQuery(query) => backend.end_query(*query)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{backend::RenderingBackend, command::CommandBuffer, error::GfxError, state::GlBackend};
+use std::ops::{Deref, DerefMut};
+
+/// Rendering context, owning one of the rendering backends.
+///
+/// All of the resource, pass and draw API is defined by `RenderingBackend`
+/// and is available directly on the context through `Deref`.
+pub struct QuadContext {
+    backend: Box<dyn RenderingBackend>,
+}
+
+impl QuadContext {
+    /// Context rendering with OpenGL through `gl`
+    pub fn new(gl: glow::Context) -> Self {
+        Self::with_backend(GlBackend::new(gl))
+    }
+
+    /// Context rendering with OpenGL loaded through `loader`, see `GlBackend::from_loader`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_loader(loader: impl FnMut(&str) -> *const std::ffi::c_void) -> Self {
+        Self::with_backend(GlBackend::from_loader(loader))
+    }
+
+    pub fn with_backend(backend: impl RenderingBackend + 'static) -> Self {
+        Self {
+            backend: Box::new(backend),
+        }
+    }
+
+    /// The backend of this context, if it is a `T`
+    pub fn backend<T: RenderingBackend + 'static>(&self) -> Option<&T> {
+        self.backend.as_any().downcast_ref()
+    }
+
+    pub fn backend_mut<T: RenderingBackend + 'static>(&mut self) -> Option<&mut T> {
+        self.backend.as_any_mut().downcast_mut()
+    }
+
+    /// Replay the commands recorded in `commands`.
+    /// Commands up to the first one with an invalid handle are executed, the rest are skipped.
+    pub fn submit(&mut self, commands: &CommandBuffer) -> Result<(), GfxError> {
+        commands.replay(&mut *self.backend)
+    }
+}
+
+impl Deref for QuadContext {
+    type Target = dyn RenderingBackend;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.backend
+    }
+}
+
+impl DerefMut for QuadContext {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut *self.backend
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    buffer::BufferId, pass::RenderPass, pipeline::Pipeline, query::QueryId, renderbuffer::RenderbufferId, shader::ShaderId,
+    texture::TextureId,
+};
+use std::{
+    ffi::{c_char, c_void},
+    sync::Mutex,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DebugSource {
+    Api,
+    WindowSystem,
+    ShaderCompiler,
+    ThirdParty,
+    Application,
+    Other,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DebugType {
+    Error,
+    DeprecatedBehavior,
+    UndefinedBehavior,
+    Portability,
+    Performance,
+    Marker,
+    PushGroup,
+    PopGroup,
+    Other,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DebugSeverity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+/// Message reported by the driver through the debug output
+#[derive(Clone, Copy, Debug)]
+pub struct DebugMessage<'a> {
+    pub source: DebugSource,
+    pub type_: DebugType,
+    /// Driver specific id of the message
+    pub id: u32,
+    pub severity: DebugSeverity,
+    pub message: &'a str,
+}
+
+impl<'a> DebugMessage<'a> {
+    pub(crate) fn from_gl(source: u32, type_: u32, id: u32, severity: u32, message: &'a str) -> Self {
+        let source = match source {
+            glow::DEBUG_SOURCE_API => DebugSource::Api,
+            glow::DEBUG_SOURCE_WINDOW_SYSTEM => DebugSource::WindowSystem,
+            glow::DEBUG_SOURCE_SHADER_COMPILER => DebugSource::ShaderCompiler,
+            glow::DEBUG_SOURCE_THIRD_PARTY => DebugSource::ThirdParty,
+            glow::DEBUG_SOURCE_APPLICATION => DebugSource::Application,
+            _ => DebugSource::Other,
+        };
+        let type_ = match type_ {
+            glow::DEBUG_TYPE_ERROR => DebugType::Error,
+            glow::DEBUG_TYPE_DEPRECATED_BEHAVIOR => DebugType::DeprecatedBehavior,
+            glow::DEBUG_TYPE_UNDEFINED_BEHAVIOR => DebugType::UndefinedBehavior,
+            glow::DEBUG_TYPE_PORTABILITY => DebugType::Portability,
+            glow::DEBUG_TYPE_PERFORMANCE => DebugType::Performance,
+            glow::DEBUG_TYPE_MARKER => DebugType::Marker,
+            glow::DEBUG_TYPE_PUSH_GROUP => DebugType::PushGroup,
+            glow::DEBUG_TYPE_POP_GROUP => DebugType::PopGroup,
+            _ => DebugType::Other,
+        };
+        let severity = match severity {
+            glow::DEBUG_SEVERITY_HIGH => DebugSeverity::High,
+            glow::DEBUG_SEVERITY_MEDIUM => DebugSeverity::Medium,
+            glow::DEBUG_SEVERITY_LOW => DebugSeverity::Low,
+            _ => DebugSeverity::Notification,
+        };
+
+        Self {
+            source,
+            type_,
+            id,
+            severity,
+            message,
+        }
+    }
+}
+
+pub type DebugCallback = Box<dyn FnMut(&DebugMessage) + Send>;
+
+/// glow 0.12 hands the driver a pointer to the callback on its own stack frame,
+/// so only a zero sized closure may be registered and the user callback lives here.
+pub(crate) static DEBUG_CALLBACK: Mutex<Option<DebugCallback>> = Mutex::new(None);
+
+pub(crate) fn dispatch(source: u32, type_: u32, id: u32, severity: u32, message: &str) {
+    // a callback calling back into GL could report another message while the lock is held
+    if let Ok(mut callback) = DEBUG_CALLBACK.try_lock() {
+        if let Some(callback) = callback.as_mut() {
+            callback(&DebugMessage::from_gl(source, type_, id, severity, message));
+        }
+    }
+}
+
+/// `glDebugMessageCallbackARB`, glow only loads the core and the `KHR_debug` entry points
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type DebugMessageCallbackArb = unsafe extern "system" fn(callback: Option<ArbCallback>, user_param: *const c_void);
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type ArbCallback =
+    extern "system" fn(source: u32, type_: u32, id: u32, severity: u32, length: i32, message: *const c_char, user_param: *mut c_void);
+
+/// Callback registered through `GL_ARB_debug_output`
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) extern "system" fn arb_callback(
+    source: u32,
+    type_: u32,
+    id: u32,
+    severity: u32,
+    length: i32,
+    message: *const c_char,
+    _user_param: *mut c_void,
+) {
+    // unwinding into the driver is undefined, a panicking user callback only loses its message
+    let _ = std::panic::catch_unwind(|| {
+        let bytes = unsafe { std::slice::from_raw_parts(message as *const u8, length.max(0) as usize) };
+        if let Ok(message) = std::str::from_utf8(bytes) {
+            dispatch(source, type_, id, severity, message);
+        }
+    });
+}
+
+/// Any of the resources of the context, used by `RenderingBackend::set_label`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Resource {
+    Buffer(BufferId),
+    Texture(TextureId),
+    Shader(ShaderId),
+    Pipeline(Pipeline),
+    RenderPass(RenderPass),
+    Query(QueryId),
+    Renderbuffer(RenderbufferId),
+}
+
+impl From<BufferId> for Resource {
+    fn from(id: BufferId) -> Self {
+        Resource::Buffer(id)
+    }
+}
+
+impl From<TextureId> for Resource {
+    fn from(id: TextureId) -> Self {
+        Resource::Texture(id)
+    }
+}
+
+impl From<ShaderId> for Resource {
+    fn from(id: ShaderId) -> Self {
+        Resource::Shader(id)
+    }
+}
+
+impl From<Pipeline> for Resource {
+    fn from(id: Pipeline) -> Self {
+        Resource::Pipeline(id)
+    }
+}
+
+impl From<RenderPass> for Resource {
+    fn from(id: RenderPass) -> Self {
+        Resource::RenderPass(id)
+    }
+}
+
+impl From<QueryId> for Resource {
+    fn from(id: QueryId) -> Self {
+        Resource::Query(id)
+    }
+}
+
+impl From<RenderbufferId> for Resource {
+    fn from(id: RenderbufferId) -> Self {
+        Resource::Renderbuffer(id)
+    }
+}
@@ -0,0 +1,88 @@
+use crate::shader::ShaderError;
+use std::{error::Error, fmt::Display};
+
+/// Kind of a resource referenced by a handle
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    Buffer,
+    Texture,
+    Shader,
+    Pipeline,
+    RenderPass,
+    Query,
+    Renderbuffer,
+}
+
+impl Display for ResourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ResourceKind::Buffer => "buffer",
+            ResourceKind::Texture => "texture",
+            ResourceKind::Shader => "shader",
+            ResourceKind::Pipeline => "pipeline",
+            ResourceKind::RenderPass => "render pass",
+            ResourceKind::Query => "query",
+            ResourceKind::Renderbuffer => "renderbuffer",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Error returned by the fallible `RenderingBackend` calls
+#[derive(Clone, Debug, PartialEq)]
+pub enum GfxError {
+    /// The handle was deleted, or was never created by this context
+    InvalidHandle(ResourceKind),
+    /// Data passed to the call is `actual` bytes long, but `expected` bytes were required
+    SizeMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    /// Updated region does not fit into the resource
+    OutOfBounds(ResourceKind),
+    /// Bindings, uniforms or vertex layout do not match what the pipeline or its shader expects
+    LayoutMismatch(String),
+    /// The requested feature or format is not supported by the backend
+    Unsupported(String),
+    /// No pipeline was applied before a call that requires one
+    NoPipeline,
+    /// The call is not allowed in the current state, e.g. ending a query that was never begun
+    InvalidState(String),
+    /// The driver failed to create an object
+    ObjectCreation {
+        kind: ResourceKind,
+        message: String,
+    },
+    Shader(ShaderError),
+}
+
+impl Display for GfxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GfxError::InvalidHandle(kind) => write!(f, "invalid or deleted {kind} handle"),
+            GfxError::SizeMismatch { expected, actual } => write!(f, "expected {expected} bytes of data, got {actual}"),
+            GfxError::OutOfBounds(kind) => write!(f, "region is out of bounds of the {kind}"),
+            GfxError::LayoutMismatch(message) => write!(f, "layout mismatch: {message}"),
+            GfxError::Unsupported(message) => write!(f, "unsupported: {message}"),
+            GfxError::NoPipeline => write!(f, "no pipeline applied"),
+            GfxError::InvalidState(message) => write!(f, "invalid state: {message}"),
+            GfxError::ObjectCreation { kind, message } => write!(f, "failed to create {kind}: {message}"),
+            GfxError::Shader(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for GfxError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GfxError::Shader(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ShaderError> for GfxError {
+    fn from(err: ShaderError) -> Self {
+        GfxError::Shader(err)
+    }
+}
@@ -104,11 +104,14 @@ impl Impl {
             Profile::Compatibility => glx::arb::GLX_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB,
         };
 
+        let flags = if config.debug { glx::arb::GLX_CONTEXT_DEBUG_BIT_ARB } else { 0 };
+
         #[rustfmt::skip]
         let ctx_attribs = [
             glx::arb::GLX_CONTEXT_MAJOR_VERSION_ARB, config.version.0 as i32,
             glx::arb::GLX_CONTEXT_MINOR_VERSION_ARB, config.version.1 as i32,
             glx::arb::GLX_CONTEXT_PROFILE_MASK_ARB, profile_mask,
+            glx::arb::GLX_CONTEXT_FLAGS_ARB, flags,
             0,
         ];
 
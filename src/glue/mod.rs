@@ -27,6 +27,8 @@ pub struct GlConfig {
     pub samples: Option<u8>,
     pub srgb: bool,
     pub double_buffer: bool,
+    /// Create a debug context, drivers may only report debug output for those
+    pub debug: bool,
 }
 
 impl Default for GlConfig {
@@ -43,6 +45,7 @@ impl Default for GlConfig {
             samples: Some(16),
             srgb: true,
             double_buffer: true,
+            debug: false,
         }
     }
 }
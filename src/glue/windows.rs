@@ -27,6 +27,9 @@ type WglCreateContextAttribsARB = extern "system" fn(HDC, HGLRC, *const i32) ->
 const WGL_CONTEXT_MAJOR_VERSION_ARB: i32 = 0x2091;
 const WGL_CONTEXT_MINOR_VERSION_ARB: i32 = 0x2092;
 const WGL_CONTEXT_PROFILE_MASK_ARB: i32 = 0x9126;
+const WGL_CONTEXT_FLAGS_ARB: i32 = 0x2094;
+
+const WGL_CONTEXT_DEBUG_BIT_ARB: i32 = 0x00000001;
 
 const WGL_CONTEXT_CORE_PROFILE_BIT_ARB: i32 = 0x00000001;
 const WGL_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB: i32 = 0x00000002;
@@ -223,12 +226,16 @@ impl Impl {
             Profile::Compatibility => WGL_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB,
         };
 
+        // a no error context can not be a debug context
+        let (flags, no_error) = if config.debug { (WGL_CONTEXT_DEBUG_BIT_ARB, 0) } else { (0, 1) };
+
         #[rustfmt::skip]
         let ctx_attribs = [
             WGL_CONTEXT_MAJOR_VERSION_ARB, config.version.0 as i32,
             WGL_CONTEXT_MINOR_VERSION_ARB, config.version.1 as i32,
             WGL_CONTEXT_PROFILE_MASK_ARB, profile_mask,
-            WGL_CONTEXT_OPENGL_NO_ERROR_ARB, 1,
+            WGL_CONTEXT_FLAGS_ARB, flags,
+            WGL_CONTEXT_OPENGL_NO_ERROR_ARB, no_error,
             0
         ];
 
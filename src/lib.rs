@@ -6,26 +6,39 @@ use glow::HasContext;
 use std::mem::transmute;
 use texture::TextureId;
 
+pub mod backend;
 pub mod buffer;
 pub mod cache;
 pub mod color;
+pub mod command;
+pub mod context;
+pub mod debug;
+pub mod error;
 pub mod glue;
 pub mod pass;
 pub mod pipeline;
+pub mod query;
+pub mod recording;
+pub mod renderbuffer;
+mod resource;
 pub mod shader;
+pub mod software;
 pub mod state;
 pub mod texture;
+mod timer;
 pub mod uniform;
 
-pub use state::QuadContext;
-
-pub const MAX_VERTEX_ATTRIBUTES: usize = 16;
-pub const MAX_SHADERSTAGE_IMAGES: usize = 12;
+pub use backend::RenderingBackend;
+pub use command::CommandBuffer;
+pub use context::QuadContext;
+pub use recording::RecordingBackend;
+pub use software::SoftwareBackend;
+pub use state::GlBackend;
 
 type ColorMask = (bool, bool, bool, bool);
 
 /// Geometry bindings
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Bindings {
     /// Vertex buffers. Data contained in the buffer must match layout
     /// specified in the `Pipeline`.
@@ -34,11 +47,33 @@ pub struct Bindings {
     /// vertex in 3d space, as well as `(u,v)` coordinates that map the vertex
     /// to some position in the corresponding `Texture`.
     pub vertex_buffers: Vec<BufferId>,
+    /// Index buffer used by `draw_indexed`. Element size of the buffer
+    /// (u8, u16 or u32) defines the index type of the draw call.
+    pub index_buffer: Option<BufferId>,
     /// Textures to be used with when drawing the geometry in the fragment
     /// shader.
     pub images: Vec<TextureId>,
 }
 
+impl Bindings {
+    /// Check that the bindings have the `images` textures and `vertex_buffers` buffers a pipeline expects
+    pub(crate) fn check_counts(&self, images: usize, vertex_buffers: usize) -> Result<(), error::GfxError> {
+        if self.images.len() < images {
+            return Err(error::GfxError::LayoutMismatch(format!(
+                "shader expects {images} images, bindings have {}",
+                self.images.len()
+            )));
+        }
+        if self.vertex_buffers.len() < vertex_buffers {
+            return Err(error::GfxError::LayoutMismatch(format!(
+                "pipeline expects {vertex_buffers} vertex buffers, bindings have {}",
+                self.vertex_buffers.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
 pub(crate) fn convert_framebuffer(data: i32) -> Option<glow::Framebuffer> {
     #[cfg(not(target_arch = "wasm32"))]
     unsafe {
@@ -1,21 +1,27 @@
 use crate::{
+    backend::RenderingBackend,
     color::Color,
-    texture::{Texture, TextureId},
+    error::{GfxError, ResourceKind},
+    renderbuffer::{self, Renderbuffer, RenderbufferId, RenderbufferParams},
+    resource::{resource_id, Handle, Resources},
+    texture::{Texture, TextureFormat, TextureId, TextureParams},
 };
 use glow::HasContext;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum PassAction {
     Nothing,
     Clear {
-        color: Option<Color>,
+        /// Applied to every color attachment, see `RenderingBackend::clear_attachment`.
+        /// `begin_pass` fails when it does not match one of them, the default pass ignores integer values.
+        color: Option<ClearValue>,
         depth: Option<f32>,
         stencil: Option<i32>,
     },
 }
 
 impl PassAction {
-    pub fn clear_color(c: impl Into<Color>) -> PassAction {
+    pub fn clear_color(c: impl Into<ClearValue>) -> PassAction {
         PassAction::Clear {
             color: Some(c.into()),
             depth: Some(1.),
@@ -27,54 +33,642 @@ impl PassAction {
 impl Default for PassAction {
     fn default() -> PassAction {
         PassAction::Clear {
-            color: Some(Color::TRANSPARENT),
+            color: Some(Color::TRANSPARENT.into()),
             depth: Some(1.),
             stencil: None,
         }
     }
 }
 
+/// Value a color attachment is cleared to, it has to match the kind of the attachment format:
+/// `Int` for `TextureFormat::R32I`, `Uint` for `TextureFormat::R32UI` and `Float` for the rest.
+/// Floats are stored as they are, normalized formats clamp them to 0..1.
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub struct RenderPass(pub(super) usize);
+pub enum ClearValue {
+    Float([f32; 4]),
+    Int([i32; 4]),
+    Uint([u32; 4]),
+}
+
+impl From<Color> for ClearValue {
+    fn from(c: Color) -> Self {
+        ClearValue::Float([c.r, c.g, c.b, c.a].map(|c| c as f32 / 255.0))
+    }
+}
+
+impl From<[f32; 4]> for ClearValue {
+    fn from(value: [f32; 4]) -> Self {
+        ClearValue::Float(value)
+    }
+}
+
+impl From<[i32; 4]> for ClearValue {
+    fn from(value: [i32; 4]) -> Self {
+        ClearValue::Int(value)
+    }
+}
+
+impl From<[u32; 4]> for ClearValue {
+    fn from(value: [u32; 4]) -> Self {
+        ClearValue::Uint(value)
+    }
+}
+
+/// Check that color attachment `index` of a pass with color attachments of `formats` can be cleared to `value`
+pub(crate) fn check_clear_value(formats: &[TextureFormat], index: usize, value: ClearValue) -> Result<(), GfxError> {
+    let Some(&format) = formats.get(index) else {
+        return Err(GfxError::LayoutMismatch(format!(
+            "clear of color attachment {index} of a pass with {} of them",
+            formats.len()
+        )));
+    };
+    let matches = match value {
+        ClearValue::Float(_) => !format.is_integer(),
+        ClearValue::Int(_) => format == TextureFormat::R32I,
+        ClearValue::Uint(_) => format == TextureFormat::R32UI,
+    };
+    if !matches {
+        return Err(GfxError::LayoutMismatch(format!("{value:?} clear of a {format:?} attachment")));
+    }
+    Ok(())
+}
+
+/// What happens to the contents of an attachment at the end of a pass
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum StoreAction {
+    /// Keep the contents, the default
+    #[default]
+    Store,
+    /// The contents are not needed after the pass and are undefined afterwards.
+    /// Tile based GPUs skip writing them back to memory.
+    Discard,
+}
+
+/// Store actions of the color, depth and stencil attachments of a pass
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct StoreActions {
+    /// Applies to all of the color attachments
+    pub color: StoreAction,
+    pub depth: StoreAction,
+    pub stencil: StoreAction,
+}
+
+impl StoreActions {
+    /// Keep the colors, discard the depth and stencil
+    pub fn discard_depth_stencil() -> Self {
+        Self {
+            color: StoreAction::Store,
+            depth: StoreAction::Discard,
+            stencil: StoreAction::Discard,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderPass(pub(crate) Handle);
+resource_id!(RenderPass, RenderPass);
+
+/// Color or depth attachment of a render pass
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Attachment {
+    /// Mip level 0 of a 2D texture, the first face or layer of the other kinds
+    Texture(TextureId),
+    /// Mip `level` of a texture, and face `layer` of a cube map or `layer` of an array texture.
+    /// The faces are numbered in the order of `TextureKind::CubeMap`.
+    TextureLevel { texture: TextureId, level: u32, layer: u32 },
+    /// Storage that is never sampled, see `RenderingBackend::new_renderbuffer`
+    Renderbuffer(RenderbufferId),
+}
+
+impl Attachment {
+    pub fn texture(self) -> Option<TextureId> {
+        match self {
+            Attachment::Texture(texture) | Attachment::TextureLevel { texture, .. } => Some(texture),
+            Attachment::Renderbuffer(_) => None,
+        }
+    }
+}
+
+impl From<TextureId> for Attachment {
+    fn from(id: TextureId) -> Self {
+        Attachment::Texture(id)
+    }
+}
+
+impl From<RenderbufferId> for Attachment {
+    fn from(id: RenderbufferId) -> Self {
+        Attachment::Renderbuffer(id)
+    }
+}
+
+/// Format, size and sample count of an attachment, as far as the render pass validation cares
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct AttachmentDesc {
+    pub format: TextureFormat,
+    /// Size of the mip level rendered to
+    pub width: u32,
+    pub height: u32,
+    /// `None` for textures, they are single sampled and get multisampled storage from the pass
+    pub samples: Option<u32>,
+    /// Mip level and face or layer rendered to, with the number of them the texture has
+    pub level: u32,
+    pub levels: u32,
+    pub layer: u32,
+    pub layers: u32,
+}
+
+impl AttachmentDesc {
+    /// Mip `level` of face or layer `layer` of a texture
+    pub fn texture(params: TextureParams, level: u32, layer: u32) -> Self {
+        let (width, height) = params.level_size(level);
+        Self {
+            format: params.format,
+            width,
+            height,
+            samples: None,
+            level,
+            levels: params.levels,
+            layer,
+            layers: params.layers(),
+        }
+    }
+}
+
+impl From<RenderbufferParams> for AttachmentDesc {
+    fn from(params: RenderbufferParams) -> Self {
+        Self {
+            format: params.format,
+            width: params.width,
+            height: params.height,
+            samples: Some(params.samples),
+            level: 0,
+            levels: 1,
+            layer: 0,
+            layers: 1,
+        }
+    }
+}
+
+/// Check that the attachments of a render pass with `samples` samples can be used together:
+/// mip levels and layers the textures have, the same size, color formats for the colors,
+/// a depth format for the depth and renderbuffers with the sample count of the pass
+pub(crate) fn check_attachments(color: &[AttachmentDesc], depth: Option<AttachmentDesc>, samples: u32) -> Result<(), GfxError> {
+    if color
+        .iter()
+        .chain(&depth)
+        .any(|desc| desc.level >= desc.levels || desc.layer >= desc.layers)
+    {
+        return Err(GfxError::OutOfBounds(ResourceKind::Texture));
+    }
+    for desc in color {
+        if desc.format.has_depth() {
+            return Err(GfxError::LayoutMismatch(format!("{:?} as a color attachment", desc.format)));
+        }
+    }
+    if let Some(desc) = depth {
+        if !desc.format.has_depth() {
+            return Err(GfxError::LayoutMismatch(format!("{:?} as a depth attachment", desc.format)));
+        }
+    }
+
+    let mut all = color.iter().chain(&depth);
+    if let Some(first) = all.clone().next() {
+        if let Some(desc) = all.clone().find(|desc| (desc.width, desc.height) != (first.width, first.height)) {
+            return Err(GfxError::LayoutMismatch(format!(
+                "render pass attachments of different sizes, {}x{} and {}x{}",
+                first.width, first.height, desc.width, desc.height
+            )));
+        }
+    }
+    if let Some(rb_samples) = all.find_map(|desc| desc.samples.filter(|rb_samples| (*rb_samples).max(1) != samples.max(1))) {
+        return Err(GfxError::LayoutMismatch(format!(
+            "renderbuffer with {rb_samples} samples in a render pass with {samples}"
+        )));
+    }
+    Ok(())
+}
+
+/// Render pass checks shared by the backends, built on the textures and renderbuffers of the backend
+pub(crate) trait PassResources: RenderingBackend {
+    fn render_pass_depth_attachment(&self, pass: RenderPass) -> Result<Option<Attachment>, GfxError>;
+
+    fn attachment_desc(&self, attachment: Attachment) -> Result<AttachmentDesc, GfxError> {
+        Ok(match attachment {
+            Attachment::Texture(texture) => AttachmentDesc::texture(self.texture_params(texture)?, 0, 0),
+            Attachment::TextureLevel { texture, level, layer } => AttachmentDesc::texture(self.texture_params(texture)?, level, layer),
+            Attachment::Renderbuffer(renderbuffer) => self.renderbuffer_params(renderbuffer)?.into(),
+        })
+    }
+
+    /// Formats of the color attachments of `pass`, the window color buffer for the default pass
+    fn color_formats(&self, pass: Option<RenderPass>) -> Result<Vec<TextureFormat>, GfxError> {
+        let Some(pass) = pass else {
+            return Ok(vec![TextureFormat::RGBA8]);
+        };
+        self.render_pass_color_attachments(pass)?
+            .iter()
+            .map(|attachment| Ok(self.attachment_desc(*attachment)?.format))
+            .collect()
+    }
+
+    /// Check that color attachment `index` of `pass` can be cleared to `value`
+    fn check_clear_attachment(&self, pass: Option<RenderPass>, index: usize, value: ClearValue) -> Result<(), GfxError> {
+        check_clear_value(&self.color_formats(pass)?, index, value)
+    }
+
+    /// Check the attachments of a new render pass, see `check_attachments`
+    fn check_new_pass(&self, color: &[Attachment], depth: Option<Attachment>, samples: u32) -> Result<(), GfxError> {
+        self.capabilities().check_color_attachments(color.len())?;
+        self.capabilities().check_samples(samples)?;
+        let color_descs = color
+            .iter()
+            .map(|attachment| self.attachment_desc(*attachment))
+            .collect::<Result<Vec<_>, _>>()?;
+        let depth_desc = depth.map(|attachment| self.attachment_desc(attachment)).transpose()?;
+        check_attachments(&color_descs, depth_desc, samples)
+    }
+
+    /// Check that the attachments of `pass` are still alive and that `action` clears the colors to values
+    /// of their formats. Returns the descriptions of the color attachments.
+    fn check_begin_pass(&self, pass: RenderPass, action: PassAction) -> Result<Vec<AttachmentDesc>, GfxError> {
+        let descs = self
+            .render_pass_color_attachments(pass)?
+            .iter()
+            .map(|attachment| self.attachment_desc(*attachment))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(depth) = self.render_pass_depth_attachment(pass)? {
+            self.attachment_desc(depth)?;
+        }
+        if let PassAction::Clear { color: Some(value), .. } = action {
+            let formats: Vec<_> = descs.iter().map(|desc| desc.format).collect();
+            for index in 0..formats.len() {
+                check_clear_value(&formats, index, value)?;
+            }
+        }
+        Ok(descs)
+    }
+
+    /// Delete the attachments of a deleted render pass, skipping the ones deleted already
+    fn delete_attachments(&mut self, attachments: impl IntoIterator<Item = Attachment>) -> Result<(), GfxError> {
+        for attachment in attachments {
+            match attachment {
+                Attachment::Texture(texture) | Attachment::TextureLevel { texture, .. } if self.texture_params(texture).is_ok() => {
+                    self.delete_texture(texture)?
+                }
+                Attachment::Renderbuffer(renderbuffer) if self.renderbuffer_params(renderbuffer).is_ok() => {
+                    self.delete_renderbuffer(renderbuffer)?
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
 
 pub(crate) struct RenderPassInternal {
-    pub gl_fb: Option<glow::Framebuffer>,
-    pub texture: TextureId,
-    pub depth_texture: Option<TextureId>,
+    /// Framebuffer rendered to, with the attachments or with the multisampled renderbuffers attached
+    pub gl_fb: glow::Framebuffer,
+    pub color_attachments: Vec<Attachment>,
+    pub depth_attachment: Option<Attachment>,
+    /// Format of the depth attachment, it has a stencil for `TextureFormat::Depth24Stencil8`
+    pub depth_format: Option<TextureFormat>,
+    pub msaa: Option<MsaaTargets>,
 }
+
+/// Multisampled storage of the texture attachments of a render pass, resolved into them at the end of the pass
+pub(crate) struct MsaaTargets {
+    /// Framebuffer with the textures attached, the destination of the resolve
+    pub resolve_fb: glow::Framebuffer,
+    pub renderbuffers: Vec<glow::Renderbuffer>,
+}
+
 impl RenderPassInternal {
+    /// Create the framebuffer and check that it is complete.
+    /// GL objects created before a failure are deleted again.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         gl: &glow::Context,
-        textures: &[Texture],
+        textures: &Resources<TextureId, Texture>,
+        renderbuffers: &Resources<RenderbufferId, Renderbuffer>,
         default_framebuffer: Option<glow::Framebuffer>,
-        color_img: TextureId,
-        depth_img: Option<TextureId>,
-    ) -> Self {
+        color: &[Attachment],
+        depth: Option<Attachment>,
+        samples: u32,
+    ) -> Result<Self, GfxError> {
         unsafe {
-            let gl_fb = gl.create_framebuffer().ok();
-            gl.bind_framebuffer(glow::FRAMEBUFFER, gl_fb);
-            gl.framebuffer_texture_2d(
-                glow::FRAMEBUFFER,
-                glow::COLOR_ATTACHMENT0,
-                glow::TEXTURE_2D,
-                textures[color_img.0].raw,
-                0,
-            );
-            if let Some(depth_img) = depth_img {
-                gl.framebuffer_texture_2d(
-                    glow::FRAMEBUFFER,
-                    glow::DEPTH_ATTACHMENT,
-                    glow::TEXTURE_2D,
-                    textures[depth_img.0].raw,
-                    0,
-                );
-            }
-            gl.bind_framebuffer(glow::FRAMEBUFFER, default_framebuffer);
-            Self {
+            let gl_fb = gl.create_framebuffer().map_err(object_creation)?;
+            let mut pass = Self {
                 gl_fb,
-                texture: color_img,
-                depth_texture: depth_img,
+                color_attachments: color.to_vec(),
+                depth_attachment: depth,
+                depth_format: depth.map(|depth| attachment_format(textures, renderbuffers, depth)),
+                msaa: None,
+            };
+            let result = pass.attach_all(gl, textures, renderbuffers, samples);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, default_framebuffer);
+            match result {
+                Ok(()) => Ok(pass),
+                Err(err) => {
+                    pass.delete(gl);
+                    Err(err)
+                }
+            }
+        }
+    }
+
+    unsafe fn attach_all(
+        &mut self,
+        gl: &glow::Context,
+        textures: &Resources<TextureId, Texture>,
+        renderbuffers: &Resources<RenderbufferId, Renderbuffer>,
+        samples: u32,
+    ) -> Result<(), GfxError> {
+        let (color, depth) = (&self.color_attachments[..], self.depth_attachment);
+
+        if samples > 1 && attachment_points(color, depth).any(|(_, attachment)| attachment.texture().is_some()) {
+            let resolve_fb = gl.create_framebuffer().map_err(object_creation)?;
+            let msaa = self.msaa.insert(MsaaTargets {
+                resolve_fb,
+                renderbuffers: vec![],
+            });
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(resolve_fb));
+            for (attachment_point, attachment) in attachment_points(color, depth) {
+                if attachment.texture().is_some() {
+                    attach(gl, textures, renderbuffers, attachment_point, attachment);
+                }
+            }
+            // `resolve` selects the buffers of every blit, none of them are read or drawn otherwise
+            gl.draw_buffers(&[glow::NONE]);
+            gl.read_buffer(glow::NONE);
+            check_framebuffer_status(gl)?;
+
+            // renderbuffer attachments are multisampled already, the textures are replaced
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.gl_fb));
+            for (attachment_point, attachment) in attachment_points(color, depth) {
+                let (texture, level) = match attachment {
+                    Attachment::Texture(texture) => (texture, 0),
+                    Attachment::TextureLevel { texture, level, .. } => (texture, level),
+                    Attachment::Renderbuffer(_) => {
+                        attach(gl, textures, renderbuffers, attachment_point, attachment);
+                        continue;
+                    }
+                };
+                let params = textures[texture].params;
+                let (width, height) = params.level_size(level);
+                let renderbuffer = gl.create_renderbuffer().map_err(object_creation)?;
+                msaa.renderbuffers.push(renderbuffer);
+                renderbuffer::storage(gl, renderbuffer, params.format, width, height, samples);
+                let attachment_point = depth_stencil_point(attachment_point, params.format);
+                gl.framebuffer_renderbuffer(glow::FRAMEBUFFER, attachment_point, glow::RENDERBUFFER, Some(renderbuffer));
+            }
+        } else {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.gl_fb));
+            for (attachment_point, attachment) in attachment_points(color, depth) {
+                attach(gl, textures, renderbuffers, attachment_point, attachment);
             }
         }
+
+        // the list of draw buffers is a part of the framebuffer state, it is set only once
+        let draw_buffers: Vec<_> = (0..color.len() as u32).map(|n| glow::COLOR_ATTACHMENT0 + n).collect();
+        gl.draw_buffers(&draw_buffers);
+        check_framebuffer_status(gl)
+    }
+
+    /// Apply the store actions at the end of the pass: resolve the multisampled renderbuffers
+    /// into the stored texture attachments, then invalidate everything that is not needed anymore
+    /// if `invalidate` is supported. Leaves the framebuffers of the pass bound.
+    pub unsafe fn finish(&self, gl: &glow::Context, width: i32, height: i32, store: StoreActions, invalidate: bool) {
+        if let Some(msaa) = &self.msaa {
+            self.resolve(gl, msaa, width, height, store);
+        }
+        if !invalidate {
+            return;
+        }
+
+        // the multisampled copies of the textures are never needed again after the resolve
+        let replaced = |attachment: Attachment| self.msaa.is_some() && attachment.texture().is_some();
+        let mut attachment_points = vec![];
+        for (n, attachment) in self.color_attachments.iter().enumerate() {
+            if replaced(*attachment) || store.color == StoreAction::Discard {
+                attachment_points.push(glow::COLOR_ATTACHMENT0 + n as u32);
+            }
+        }
+        if let (Some(attachment), Some(format)) = (self.depth_attachment, self.depth_format) {
+            if replaced(attachment) || store.depth == StoreAction::Discard {
+                attachment_points.push(glow::DEPTH_ATTACHMENT);
+            }
+            if format.has_stencil() && (replaced(attachment) || store.stencil == StoreAction::Discard) {
+                attachment_points.push(glow::STENCIL_ATTACHMENT);
+            }
+        }
+        if !attachment_points.is_empty() {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.gl_fb));
+            gl.invalidate_framebuffer(glow::FRAMEBUFFER, &attachment_points);
+        }
+    }
+
+    /// Copy the multisampled renderbuffers into the stored texture attachments of the pass, averaging the samples
+    unsafe fn resolve(&self, gl: &glow::Context, msaa: &MsaaTargets, width: i32, height: i32, store: StoreActions) {
+        let (w, h) = (width, height);
+
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.gl_fb));
+        gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(msaa.resolve_fb));
+        // blits are scissored, the scissor test is enabled again by the next `apply_pipeline`
+        gl.disable(glow::SCISSOR_TEST);
+
+        // a blit reads a single color buffer, the attachments are resolved one at a time
+        let mut draw_buffers = vec![glow::NONE; self.color_attachments.len()];
+        for (n, attachment) in self.color_attachments.iter().enumerate() {
+            if attachment.texture().is_none() || store.color == StoreAction::Discard {
+                continue;
+            }
+            let attachment_point = glow::COLOR_ATTACHMENT0 + n as u32;
+            draw_buffers[n] = attachment_point;
+            gl.read_buffer(attachment_point);
+            gl.draw_buffers(&draw_buffers);
+            gl.blit_framebuffer(0, 0, w, h, 0, 0, w, h, glow::COLOR_BUFFER_BIT, glow::NEAREST);
+            draw_buffers[n] = glow::NONE;
+        }
+        if let (Some(Attachment::Texture(_) | Attachment::TextureLevel { .. }), Some(format)) = (self.depth_attachment, self.depth_format) {
+            let mut mask = 0;
+            if store.depth == StoreAction::Store {
+                mask |= glow::DEPTH_BUFFER_BIT;
+            }
+            if format.has_stencil() && store.stencil == StoreAction::Store {
+                mask |= glow::STENCIL_BUFFER_BIT;
+            }
+            if mask != 0 {
+                gl.blit_framebuffer(0, 0, w, h, 0, 0, w, h, mask, glow::NEAREST);
+            }
+        }
+
+        gl.read_buffer(glow::COLOR_ATTACHMENT0);
+    }
+
+    pub unsafe fn delete(&self, gl: &glow::Context) {
+        gl.delete_framebuffer(self.gl_fb);
+        if let Some(msaa) = &self.msaa {
+            gl.delete_framebuffer(msaa.resolve_fb);
+            for renderbuffer in &msaa.renderbuffers {
+                gl.delete_renderbuffer(*renderbuffer);
+            }
+        }
+    }
+}
+
+fn object_creation(message: String) -> GfxError {
+    GfxError::ObjectCreation {
+        kind: ResourceKind::RenderPass,
+        message,
+    }
+}
+
+/// Error describing why the framebuffer bound to `GL_FRAMEBUFFER` can not be rendered to
+unsafe fn check_framebuffer_status(gl: &glow::Context) -> Result<(), GfxError> {
+    let message = match gl.check_framebuffer_status(glow::FRAMEBUFFER) {
+        glow::FRAMEBUFFER_COMPLETE => return Ok(()),
+        glow::FRAMEBUFFER_UNSUPPORTED => return Err(GfxError::Unsupported("combination of render pass attachment formats".to_string())),
+        glow::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "incomplete attachment, its format can not be rendered to".to_string(),
+        glow::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "missing attachment".to_string(),
+        glow::FRAMEBUFFER_INCOMPLETE_DIMENSIONS => "attachments of different sizes".to_string(),
+        glow::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "attachments with different sample counts".to_string(),
+        glow::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER | glow::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => {
+            "draw or read buffer without an attachment".to_string()
+        }
+        status => format!("incomplete framebuffer, status {status:#x}"),
+    };
+    Err(object_creation(message))
+}
+
+/// Attachment points of the color and depth attachments of a render pass
+fn attachment_points(color: &[Attachment], depth: Option<Attachment>) -> impl Iterator<Item = (u32, Attachment)> + '_ {
+    let colors = color
+        .iter()
+        .enumerate()
+        .map(|(n, attachment)| (glow::COLOR_ATTACHMENT0 + n as u32, *attachment));
+    colors.chain(depth.map(|depth| (glow::DEPTH_ATTACHMENT, depth)))
+}
+
+/// `GL_DEPTH_STENCIL_ATTACHMENT` in place of `GL_DEPTH_ATTACHMENT` for the formats with a stencil
+fn depth_stencil_point(attachment_point: u32, format: TextureFormat) -> u32 {
+    if attachment_point == glow::DEPTH_ATTACHMENT && format.has_stencil() {
+        glow::DEPTH_STENCIL_ATTACHMENT
+    } else {
+        attachment_point
+    }
+}
+
+fn attachment_format(
+    textures: &Resources<TextureId, Texture>,
+    renderbuffers: &Resources<RenderbufferId, Renderbuffer>,
+    attachment: Attachment,
+) -> TextureFormat {
+    match attachment {
+        Attachment::Texture(texture) | Attachment::TextureLevel { texture, .. } => textures[texture].params.format,
+        Attachment::Renderbuffer(renderbuffer) => renderbuffers[renderbuffer].params.format,
+    }
+}
+
+/// Attach `attachment` to the framebuffer bound to `GL_FRAMEBUFFER`
+unsafe fn attach(
+    gl: &glow::Context,
+    textures: &Resources<TextureId, Texture>,
+    renderbuffers: &Resources<RenderbufferId, Renderbuffer>,
+    attachment_point: u32,
+    attachment: Attachment,
+) {
+    let attachment_point = depth_stencil_point(attachment_point, attachment_format(textures, renderbuffers, attachment));
+    match attachment {
+        Attachment::Texture(texture) => textures[texture].attach(gl, attachment_point, 0, 0),
+        Attachment::TextureLevel { texture, level, layer } => textures[texture].attach(gl, attachment_point, level, layer),
+        Attachment::Renderbuffer(renderbuffer) => gl.framebuffer_renderbuffer(
+            glow::FRAMEBUFFER,
+            attachment_point,
+            glow::RENDERBUFFER,
+            Some(renderbuffers[renderbuffer].raw),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::TextureKind;
+
+    fn desc(format: TextureFormat, width: u32, samples: Option<u32>) -> AttachmentDesc {
+        AttachmentDesc {
+            format,
+            width,
+            height: 4,
+            samples,
+            level: 0,
+            levels: 1,
+            layer: 0,
+            layers: 1,
+        }
+    }
+
+    fn is_mismatch(result: Result<(), GfxError>) -> bool {
+        matches!(result, Err(GfxError::LayoutMismatch(_)))
+    }
+
+    #[test]
+    fn valid_attachments() {
+        let color = [desc(TextureFormat::RGBA8, 4, None), desc(TextureFormat::RGB8, 4, None)];
+        assert!(check_attachments(&color, Some(desc(TextureFormat::Depth24Stencil8, 4, None)), 1).is_ok());
+        assert!(check_attachments(&color, None, 4).is_ok());
+        // single sampled renderbuffers may say 0 or 1 samples
+        let color = [desc(TextureFormat::RGBA8, 4, Some(0))];
+        assert!(check_attachments(&color, Some(desc(TextureFormat::Depth, 4, Some(1))), 1).is_ok());
+    }
+
+    #[test]
+    fn formats_in_the_wrong_place() {
+        let color = [desc(TextureFormat::RGBA8, 4, None)];
+        assert!(is_mismatch(check_attachments(&[desc(TextureFormat::Depth, 4, None)], None, 1)));
+        assert!(is_mismatch(check_attachments(&color, Some(desc(TextureFormat::RGBA8, 4, None)), 1)));
+    }
+
+    #[test]
+    fn different_sizes() {
+        let color = [desc(TextureFormat::RGBA8, 4, None), desc(TextureFormat::RGBA8, 8, None)];
+        assert!(is_mismatch(check_attachments(&color, None, 1)));
+        let color = [desc(TextureFormat::RGBA8, 4, None)];
+        assert!(is_mismatch(check_attachments(&color, Some(desc(TextureFormat::Depth, 8, None)), 1)));
+    }
+
+    #[test]
+    fn renderbuffer_samples() {
+        let color = [desc(TextureFormat::RGBA8, 4, Some(4))];
+        assert!(check_attachments(&color, None, 4).is_ok());
+        assert!(is_mismatch(check_attachments(&color, None, 1)));
+        assert!(is_mismatch(check_attachments(
+            &color,
+            Some(desc(TextureFormat::Depth, 4, Some(2))),
+            4
+        )));
+    }
+
+    #[test]
+    fn levels_and_layers() {
+        let params = |kind, levels| TextureParams {
+            width: 16,
+            height: 16,
+            kind,
+            levels,
+            ..Default::default()
+        };
+        // level 2 of a 16x16 texture matches a 4x4 attachment
+        let cube = AttachmentDesc::texture(params(TextureKind::CubeMap, 3), 2, 5);
+        assert_eq!((cube.width, cube.height), (4, 4));
+        assert!(check_attachments(&[cube], Some(desc(TextureFormat::Depth, 4, None)), 1).is_ok());
+        let array = AttachmentDesc::texture(params(TextureKind::Array(4), 3), 2, 3);
+        assert!(check_attachments(&[cube, array], None, 1).is_ok());
+
+        let out_of_bounds = |desc| matches!(check_attachments(&[desc], None, 1), Err(GfxError::OutOfBounds(_)));
+        assert!(out_of_bounds(AttachmentDesc::texture(params(TextureKind::CubeMap, 3), 3, 0)));
+        assert!(out_of_bounds(AttachmentDesc::texture(params(TextureKind::CubeMap, 3), 0, 6)));
+        assert!(out_of_bounds(AttachmentDesc::texture(params(TextureKind::Array(4), 1), 0, 4)));
+        assert!(out_of_bounds(AttachmentDesc::texture(params(TextureKind::Texture2D, 1), 0, 1)));
     }
 }
@@ -3,6 +3,9 @@ use glow::HasContext;
 use crate::{
     buffer::{BufferLayout, VertexStep},
     cache::VertexAttributeInternal,
+    color::Color,
+    error::GfxError,
+    resource::{resource_id, Handle},
     shader::ShaderId,
     ColorMask,
 };
@@ -11,6 +14,8 @@ pub(crate) struct PipelineInternal {
     pub layout: Vec<Option<VertexAttributeInternal>>,
     pub shader: ShaderId,
     pub params: PipelineParams,
+    /// Name of the debug group the draws of this pipeline are recorded in
+    pub label: Option<String>,
 }
 impl PipelineInternal {
     pub(crate) fn new(
@@ -20,7 +25,9 @@ impl PipelineInternal {
         shader: ShaderId,
         program: glow::Program,
         params: PipelineParams,
-    ) -> PipelineInternal {
+    ) -> Result<PipelineInternal, GfxError> {
+        check_attributes(buffer_layout, attributes)?;
+
         #[derive(Clone, Copy, Default)]
         struct BufferCacheData {
             stride: i32,
@@ -39,16 +46,15 @@ impl PipelineInternal {
                 cache.stride = layout.stride;
             }
             // WebGL 1 limitation
-            assert!(cache.stride <= 255);
+            if cache.stride > 255 {
+                return Err(GfxError::LayoutMismatch(format!(
+                    "stride of {} bytes of buffer {buffer_index} exceeds 255 bytes",
+                    cache.stride
+                )));
+            }
         }
 
-        let attributes_len = attributes
-            .iter()
-            .map(|layout| match layout.format {
-                VertexFormat::Mat4 => 4,
-                _ => 1,
-            })
-            .sum();
+        let attributes_len = attributes.iter().map(|layout| layout.format.slots()).sum();
 
         let mut vertex_layout = vec![None; attributes_len];
 
@@ -88,31 +94,50 @@ impl PipelineInternal {
                         divisor,
                     };
 
-                    assert!(
-                        attr_loc < vertex_layout.len() as u32,
-                        "attribute: {name} outside of allocated attributes array len: {}",
-                        vertex_layout.len()
-                    );
+                    if attr_loc >= vertex_layout.len() as u32 {
+                        return Err(GfxError::LayoutMismatch(format!(
+                            "attribute: {name} outside of allocated attributes array len: {}",
+                            vertex_layout.len()
+                        )));
+                    }
                     vertex_layout[attr_loc as usize] = Some(attr);
                 }
                 buffer_data.offset += format.size_bytes() as i64
             }
         }
 
-        Self {
+        Ok(Self {
             layout: vertex_layout,
             shader,
             params,
+            label: None,
+        })
+    }
+}
+
+/// Check that every attribute reads from one of the buffers of `buffer_layout`
+pub(crate) fn check_attributes(buffer_layout: &[BufferLayout], attributes: &[VertexAttribute]) -> Result<(), GfxError> {
+    for VertexAttribute { name, buffer_index, .. } in attributes {
+        if *buffer_index >= buffer_layout.len() {
+            return Err(GfxError::LayoutMismatch(format!(
+                "attribute {name} reads from buffer {buffer_index}, the layout has {} buffers",
+                buffer_layout.len()
+            )));
         }
     }
+    Ok(())
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct PipelineParams {
     pub cull_face: CullFace,
     pub front_face_order: FrontFaceOrder,
+    /// Comparison of the fragment depth with the depth buffer, applied independently of `depth_write`.
+    /// `Comparison::Always` without `depth_write` disables the depth test.
     pub depth_test: Comparison,
     pub depth_write: bool,
+    /// `(factor, units)` of the depth offset added to filled polygons, the same as `glPolygonOffset`.
+    /// Moves decals in front of the surface they lie on, or shadow casters away from the light.
     pub depth_write_offset: Option<(f32, f32)>,
     /// Color (RGB) blend function. If None - blending will be disabled for this pipeline.
     /// Usual use case to get alpha-blending:
@@ -148,13 +173,61 @@ pub struct PipelineParams {
     ///```
     /// The same results may be achieved with ColorMask(true, true, true, false)
     pub alpha_blend: Option<BlendState>,
+    /// Constant used by the `BlendFactor::Constant*` factors
+    pub blend_color: Color,
     pub stencil_test: Option<StencilState>,
     pub color_write: ColorMask,
     pub primitive_type: PrimitiveType,
+    /// Color write mask and blending of every color attachment of a render pass with several of them.
+    /// `None` uses `color_write`, `color_blend` and `alpha_blend` of the pipeline.
+    /// Different states for different attachments require `Features::independent_blend`.
+    pub attachments: [Option<AttachmentParams>; MAX_COLOR_ATTACHMENTS],
+}
+
+impl PipelineParams {
+    /// Color write mask and blending of the color attachment `n`
+    pub fn attachment(&self, n: usize) -> AttachmentParams {
+        self.attachments[n].unwrap_or(AttachmentParams {
+            color_write: self.color_write,
+            color_blend: self.color_blend,
+            alpha_blend: self.alpha_blend,
+        })
+    }
+
+    /// Check that no blending sets `alpha_blend` without `color_blend`
+    pub(crate) fn check_blend(&self) -> Result<(), GfxError> {
+        let pipeline = (self.color_blend, self.alpha_blend);
+        let attachments = self.attachments.iter().flatten().map(|a| (a.color_blend, a.alpha_blend));
+        if std::iter::once(pipeline)
+            .chain(attachments)
+            .any(|(color, alpha)| color.is_none() && alpha.is_some())
+        {
+            return Err(GfxError::LayoutMismatch("alpha_blend without color_blend".to_string()));
+        }
+        Ok(())
+    }
+
+    /// All of the color attachments are written and blended the same way
+    pub(crate) fn uniform_attachments(&self) -> bool {
+        (1..MAX_COLOR_ATTACHMENTS).all(|n| self.attachment(n) == self.attachment(0))
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
-pub struct Pipeline(pub(crate) usize);
+/// Most color attachments of a render pass, the device may support less of them,
+/// see `Capabilities::max_color_attachments`
+pub const MAX_COLOR_ATTACHMENTS: usize = 8;
+
+/// Color write mask and blending of a single color attachment
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct AttachmentParams {
+    pub color_write: ColorMask,
+    pub color_blend: Option<BlendState>,
+    pub alpha_blend: Option<BlendState>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Pipeline(pub(crate) Handle);
+resource_id!(Pipeline, Pipeline);
 
 impl Default for PipelineParams {
     fn default() -> PipelineParams {
@@ -166,9 +239,11 @@ impl Default for PipelineParams {
             depth_write_offset: None,
             color_blend: None,
             alpha_blend: None,
+            blend_color: Color::default(),
             stencil_test: None,
             color_write: (true, true, true, true),
             primitive_type: PrimitiveType::Triangles,
+            attachments: [None; MAX_COLOR_ATTACHMENTS],
         }
     }
 }
@@ -264,6 +339,14 @@ impl VertexFormat {
         }
     }
 
+    /// Number of attribute slots taken by this VertexFormat, matrices take a slot per column
+    pub(crate) fn slots(&self) -> usize {
+        match self {
+            VertexFormat::Mat4 => 4,
+            _ => 1,
+        }
+    }
+
     /// Size in bytes
     pub(super) fn size_bytes(&self) -> i32 {
         match self {
@@ -445,6 +528,10 @@ pub enum Equation {
     /// Subtracts source from destination. Source and destination are
     /// multiplied by blending parameters before subtraction.
     ReverseSubtract = glow::FUNC_REVERSE_SUBTRACT as _,
+    /// Minimum of source and destination, blend factors are ignored.
+    Min = glow::MIN as _,
+    /// Maximum of source and destination, blend factors are ignored.
+    Max = glow::MAX as _,
 }
 
 /// Blend values.
@@ -464,6 +551,12 @@ pub enum BlendFactor {
     Value(BlendValue),
     OneMinusValue(BlendValue),
     SourceAlphaSaturate,
+    /// `PipelineParams::blend_color`
+    ConstantColor,
+    OneMinusConstantColor,
+    /// Alpha of `PipelineParams::blend_color`
+    ConstantAlpha,
+    OneMinusConstantAlpha,
 }
 
 impl From<BlendFactor> for u32 {
@@ -480,6 +573,10 @@ impl From<BlendFactor> for u32 {
             BlendFactor::OneMinusValue(BlendValue::DestinationColor) => glow::ONE_MINUS_DST_COLOR,
             BlendFactor::OneMinusValue(BlendValue::DestinationAlpha) => glow::ONE_MINUS_DST_ALPHA,
             BlendFactor::SourceAlphaSaturate => glow::SRC_ALPHA_SATURATE,
+            BlendFactor::ConstantColor => glow::CONSTANT_COLOR,
+            BlendFactor::OneMinusConstantColor => glow::ONE_MINUS_CONSTANT_COLOR,
+            BlendFactor::ConstantAlpha => glow::CONSTANT_ALPHA,
+            BlendFactor::OneMinusConstantAlpha => glow::ONE_MINUS_CONSTANT_ALPHA,
         }
     }
 }
@@ -487,5 +584,11 @@ impl From<BlendFactor> for u32 {
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum PrimitiveType {
     Triangles = glow::TRIANGLES as _,
+    TriangleStrip = glow::TRIANGLE_STRIP as _,
+    TriangleFan = glow::TRIANGLE_FAN as _,
     Lines = glow::LINES as _,
+    LineStrip = glow::LINE_STRIP as _,
+    LineLoop = glow::LINE_LOOP as _,
+    /// Point size is taken from `gl_PointSize` written by the vertex shader
+    Points = glow::POINTS as _,
 }
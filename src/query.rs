@@ -0,0 +1,69 @@
+use crate::error::GfxError;
+use crate::resource::{resource_id, Handle};
+
+/// What a query counts between `begin_query` and `end_query`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QueryType {
+    /// 1 if any fragment passed the depth and stencil tests, 0 otherwise
+    AnySamplesPassed = glow::ANY_SAMPLES_PASSED as _,
+    /// Number of fragments that passed the depth and stencil tests, not available on GLES and WebGL
+    SamplesPassed = glow::SAMPLES_PASSED as _,
+    /// Number of primitives sent to the rasterizer, before clipping and culling.
+    /// Requires GLES 3.2 on embedded targets.
+    PrimitivesGenerated = glow::PRIMITIVES_GENERATED as _,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryId(pub(crate) Handle);
+resource_id!(QueryId, Query);
+
+pub(crate) struct QueryInternal {
+    pub raw: glow::Query,
+    pub query_type: QueryType,
+    /// Desktop GL creates the query object on the first `begin_query`, it can not be labeled before
+    pub begun: bool,
+    pub label: Option<String>,
+}
+
+/// Queries between `begin_query` and `end_query`, only one query of every type may be active at a time
+#[derive(Default)]
+pub(crate) struct ActiveQueries(Vec<(QueryType, QueryId)>);
+
+impl ActiveQueries {
+    pub fn begin(&mut self, query_type: QueryType, query: QueryId) -> Result<(), GfxError> {
+        if self.0.iter().any(|(t, _)| *t == query_type) {
+            return Err(GfxError::InvalidState(format!("a {query_type:?} query is already active")));
+        }
+        self.0.push((query_type, query));
+        Ok(())
+    }
+
+    pub fn end(&mut self, query: QueryId) -> Result<(), GfxError> {
+        match self.0.iter().position(|(_, q)| *q == query) {
+            Some(index) => {
+                self.0.swap_remove(index);
+                Ok(())
+            }
+            None => Err(GfxError::InvalidState("end_query on a query that is not active".to_string())),
+        }
+    }
+
+    /// A query has a result once it was begun and ended
+    pub fn check_ended(&self, query: QueryId, begun: bool) -> Result<(), GfxError> {
+        if !begun {
+            return Err(GfxError::InvalidState("query_result on a query that was never begun".to_string()));
+        }
+        if self.contains(query) {
+            return Err(GfxError::InvalidState("query_result on a query that is still active".to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, query: QueryId) -> bool {
+        self.0.iter().any(|(_, q)| *q == query)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = QueryId> + '_ {
+        self.0.iter().map(|(_, q)| *q)
+    }
+}
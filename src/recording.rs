@@ -0,0 +1,674 @@
+use crate::{
+    backend::*, buffer::*, color::*, debug::*, error::*, pass::*, pipeline::*, query::*, renderbuffer::*, resource::*, shader::*,
+    texture::*, uniform::*, *,
+};
+use std::{any::Any, collections::HashSet};
+
+/// A single call made to the `RecordingBackend`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call {
+    NewShader(ShaderId),
+    DeleteShader(ShaderId),
+    NewTexture {
+        texture: TextureId,
+        access: TextureAccess,
+        params: TextureParams,
+    },
+    DeleteTexture(TextureId),
+    TextureSetFilter(TextureId, FilterMode),
+    TextureSetWrap(TextureId, TextureWrap),
+    TextureResize {
+        texture: TextureId,
+        width: u32,
+        height: u32,
+    },
+    TextureUpdatePart {
+        texture: TextureId,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        bytes: Vec<u8>,
+    },
+    NewRenderbuffer {
+        renderbuffer: RenderbufferId,
+        params: RenderbufferParams,
+    },
+    DeleteRenderbuffer(RenderbufferId),
+    NewRenderPass {
+        pass: RenderPass,
+        color: Vec<Attachment>,
+        depth: Option<Attachment>,
+        samples: u32,
+    },
+    DeleteRenderPass(RenderPass),
+    NewPipeline {
+        pipeline: Pipeline,
+        shader: ShaderId,
+        params: PipelineParams,
+    },
+    DeletePipeline(Pipeline),
+    NewBuffer {
+        buffer: BufferId,
+        type_: BufferType,
+        usage: BufferUsage,
+        size: usize,
+    },
+    BufferUpdate {
+        buffer: BufferId,
+        bytes: Vec<u8>,
+    },
+    DeleteBuffer(BufferId),
+    NewQuery {
+        query: QueryId,
+        query_type: QueryType,
+    },
+    DeleteQuery(QueryId),
+    BeginQuery(QueryId),
+    EndQuery(QueryId),
+    SetLabel {
+        resource: Resource,
+        label: Option<String>,
+    },
+    ApplyPipeline(Pipeline),
+    ApplyBindings(Bindings),
+    ApplyUniforms(Vec<u8>),
+    ApplyViewport {
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+    },
+    ApplyScissorRect {
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+    },
+    Clear {
+        color: Option<Color>,
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    },
+    ClearAttachment {
+        index: usize,
+        value: ClearValue,
+    },
+    BeginDefaultPass(PassAction, StoreActions),
+    BeginPass(RenderPass, PassAction, StoreActions),
+    EndRenderPass,
+    CommitFrame,
+    Draw {
+        first: i32,
+        count: i32,
+        instance_count: i32,
+    },
+    DrawIndexed {
+        first_index: i32,
+        count: i32,
+        instance_count: i32,
+    },
+}
+
+struct RecordedTexture {
+    params: TextureParams,
+    bytes: Vec<u8>,
+}
+
+struct RecordedBuffer {
+    buffer_type: BufferType,
+    size: usize,
+    index_type: Option<u32>,
+}
+
+struct RecordedPipeline {
+    shader: ShaderId,
+    /// Vertex buffers the attributes read from
+    vertex_buffers: usize,
+}
+
+struct RecordedQuery {
+    query_type: QueryType,
+    begun: bool,
+}
+
+struct RecordedPass {
+    color_attachments: Vec<Attachment>,
+    depth_attachment: Option<Attachment>,
+}
+
+/// Backend that renders nothing and needs no GPU.
+///
+/// Every call is validated the same way the GL backend does it and appended
+/// to a log, split into frames by `commit_frame`. Texture contents are kept
+/// in memory, so `texture_read_pixels` returns the last uploaded data.
+/// Queries count nothing, their results are always available and 0.
+/// Limits are the minimums guaranteed by GLES 3, so code validated here runs on any device.
+pub struct RecordingBackend {
+    calls: Vec<Call>,
+    /// Index in `calls` of the first call of every frame
+    frame_starts: Vec<usize>,
+
+    shaders: Resources<ShaderId, ShaderMeta>,
+    pipelines: Resources<Pipeline, RecordedPipeline>,
+    passes: Resources<RenderPass, RecordedPass>,
+    buffers: Resources<BufferId, RecordedBuffer>,
+    textures: Resources<TextureId, RecordedTexture>,
+    queries: Resources<QueryId, RecordedQuery>,
+    renderbuffers: Resources<RenderbufferId, RenderbufferParams>,
+    active_queries: ActiveQueries,
+
+    cur_pipeline: Option<Pipeline>,
+    /// Offscreen pass in progress, checked by `clear_attachment`
+    cur_pass: Option<RenderPass>,
+    index_buffer: Option<BufferId>,
+    features: Features,
+    capabilities: Capabilities,
+    /// Counters of the frame in progress
+    stats: FrameStats,
+    frame_stats: FrameStats,
+}
+
+impl Default for RecordingBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self {
+            calls: vec![],
+            frame_starts: vec![0],
+            shaders: Resources::new(),
+            pipelines: Resources::new(),
+            passes: Resources::new(),
+            buffers: Resources::new(),
+            textures: Resources::new(),
+            queries: Resources::new(),
+            renderbuffers: Resources::new(),
+            active_queries: ActiveQueries::default(),
+            cur_pipeline: None,
+            cur_pass: None,
+            index_buffer: None,
+            features: Features {
+                instancing: true,
+                points: true,
+                loop_and_fan: true,
+                timer_queries: false,
+                sample_count_queries: false,
+                primitive_queries: false,
+                independent_blend: false,
+                framebuffer_invalidation: true,
+            },
+            capabilities: Capabilities {
+                max_texture_size: 2048,
+                max_texture_units: 16,
+                max_vertex_attributes: 16,
+                max_samples: 4,
+                max_color_attachments: 4,
+                glsl_version: Some(300),
+                extensions: HashSet::new(),
+            },
+            stats: FrameStats::default(),
+            frame_stats: FrameStats::default(),
+        }
+    }
+
+    /// All the recorded calls, in order
+    pub fn calls(&self) -> &[Call] {
+        &self.calls
+    }
+
+    /// Number of frames, including the one that is not committed yet
+    pub fn frame_count(&self) -> usize {
+        self.frame_starts.len()
+    }
+
+    /// Calls of the frame `n`, from the previous `commit_frame` up to and including its own one
+    pub fn frame(&self, n: usize) -> Option<&[Call]> {
+        let start = *self.frame_starts.get(n)?;
+        let end = self.frame_starts.get(n + 1).copied().unwrap_or(self.calls.len());
+        Some(&self.calls[start..end])
+    }
+
+    /// Forget all the recorded calls, resources stay alive
+    pub fn clear_calls(&mut self) {
+        self.calls.clear();
+        self.frame_starts = vec![0];
+    }
+
+    fn record(&mut self, call: Call) {
+        self.calls.push(call);
+    }
+
+    fn assert_pipeline(&self) {
+        assert!(self.cur_pipeline.is_some(), "Drawing without any binded pipeline");
+    }
+}
+
+impl PassResources for RecordingBackend {
+    fn render_pass_depth_attachment(&self, pass: RenderPass) -> Result<Option<Attachment>, GfxError> {
+        Ok(self.passes.get(pass)?.depth_attachment)
+    }
+}
+
+impl RenderingBackend for RecordingBackend {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn features(&self) -> &Features {
+        &self.features
+    }
+
+    fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    fn resize(&mut self, _width: i32, _height: i32) {}
+
+    fn new_shader(&mut self, _shader: ShaderSource, meta: ShaderMeta) -> Result<ShaderId, GfxError> {
+        self.capabilities.check_images(meta.images.len())?;
+        let shader = self.shaders.insert(meta);
+        self.record(Call::NewShader(shader));
+        Ok(shader)
+    }
+
+    fn delete_shader(&mut self, shader: ShaderId) -> Result<(), GfxError> {
+        self.shaders.remove(shader)?;
+        if let Some(pipeline) = self.cur_pipeline {
+            if self.pipelines.get(pipeline).is_ok_and(|p| p.shader == shader) {
+                self.cur_pipeline = None;
+            }
+        }
+        self.record(Call::DeleteShader(shader));
+        Ok(())
+    }
+
+    fn new_texture(&mut self, access: TextureAccess, bytes: Option<&[u8]>, params: TextureParams) -> Result<TextureId, GfxError> {
+        self.capabilities.check_texture_size(params.width, params.height)?;
+        params.check_levels()?;
+        if let Some(bytes) = bytes {
+            params.check_size(params.width, params.height, bytes)?;
+        }
+
+        let texture = self.textures.insert(RecordedTexture {
+            params,
+            bytes: params.storage(bytes),
+        });
+        self.record(Call::NewTexture { texture, access, params });
+        Ok(texture)
+    }
+
+    fn delete_texture(&mut self, texture: TextureId) -> Result<(), GfxError> {
+        self.textures.remove(texture)?;
+        self.record(Call::DeleteTexture(texture));
+        Ok(())
+    }
+
+    fn texture_params(&self, texture: TextureId) -> Result<TextureParams, GfxError> {
+        Ok(self.textures.get(texture)?.params)
+    }
+
+    fn texture_set_filter(&mut self, texture: TextureId, filter: FilterMode) -> Result<(), GfxError> {
+        self.textures.get_mut(texture)?.params.filter = filter;
+        self.record(Call::TextureSetFilter(texture, filter));
+        Ok(())
+    }
+
+    fn texture_set_wrap(&mut self, texture: TextureId, wrap: TextureWrap) -> Result<(), GfxError> {
+        self.textures.get_mut(texture)?.params.wrap = wrap;
+        self.record(Call::TextureSetWrap(texture, wrap));
+        Ok(())
+    }
+
+    fn texture_resize(&mut self, texture: TextureId, width: u32, height: u32, bytes: Option<&[u8]>) -> Result<(), GfxError> {
+        self.capabilities.check_texture_size(width, height)?;
+        let t = self.textures.get_mut(texture)?;
+        let params = TextureParams { width, height, ..t.params };
+        params.check_levels()?;
+        if let Some(bytes) = bytes {
+            params.check_size(width, height, bytes)?;
+        }
+        t.params = params;
+        t.bytes = params.storage(bytes);
+
+        self.record(Call::TextureResize { texture, width, height });
+        Ok(())
+    }
+
+    fn texture_read_pixels(&mut self, texture: TextureId, bytes: &mut [u8]) -> Result<(), GfxError> {
+        let t = self.textures.get(texture)?;
+        t.params.check_read(bytes)?;
+        let image = &t.bytes[t.params.image_range(0, 0)];
+        bytes[..image.len()].copy_from_slice(image);
+        Ok(())
+    }
+
+    fn texture_update_part(
+        &mut self,
+        texture: TextureId,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        bytes: &[u8],
+    ) -> Result<(), GfxError> {
+        let t = self.textures.get_mut(texture)?;
+        t.params.check_region(x_offset, y_offset, width, height, bytes)?;
+        let format = t.params.format;
+
+        let pixel_size = format.pixel_size();
+        let src_row = width as usize * pixel_size;
+        let dst_row = t.params.width as usize * pixel_size;
+        // a zero width region has no bytes, `chunks_exact` only rejects the zero size
+        for (y, row) in bytes.chunks_exact(src_row.max(1)).enumerate() {
+            let start = (y_offset as usize + y) * dst_row + x_offset as usize * pixel_size;
+            t.bytes[start..start + src_row].copy_from_slice(row);
+        }
+        self.stats.bytes_uploaded += bytes.len() as u64;
+
+        self.record(Call::TextureUpdatePart {
+            texture,
+            x_offset,
+            y_offset,
+            width,
+            height,
+            bytes: bytes.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn new_renderbuffer(&mut self, format: TextureFormat, width: u32, height: u32, samples: u32) -> Result<RenderbufferId, GfxError> {
+        self.capabilities.check_texture_size(width, height)?;
+        self.capabilities.check_samples(samples)?;
+        let params = RenderbufferParams {
+            format,
+            width,
+            height,
+            samples: samples.max(1),
+        };
+        let renderbuffer = self.renderbuffers.insert(params);
+        self.record(Call::NewRenderbuffer { renderbuffer, params });
+        Ok(renderbuffer)
+    }
+
+    fn renderbuffer_params(&self, renderbuffer: RenderbufferId) -> Result<RenderbufferParams, GfxError> {
+        Ok(*self.renderbuffers.get(renderbuffer)?)
+    }
+
+    fn delete_renderbuffer(&mut self, renderbuffer: RenderbufferId) -> Result<(), GfxError> {
+        self.renderbuffers.remove(renderbuffer)?;
+        self.record(Call::DeleteRenderbuffer(renderbuffer));
+        Ok(())
+    }
+
+    fn new_render_pass_with_attachments(
+        &mut self,
+        color: &[Attachment],
+        depth: Option<Attachment>,
+        samples: u32,
+    ) -> Result<RenderPass, GfxError> {
+        self.check_new_pass(color, depth, samples)?;
+
+        let pass = self.passes.insert(RecordedPass {
+            color_attachments: color.to_vec(),
+            depth_attachment: depth,
+        });
+        self.record(Call::NewRenderPass {
+            pass,
+            color: color.to_vec(),
+            depth,
+            samples,
+        });
+        Ok(pass)
+    }
+
+    fn render_pass_color_attachments(&self, pass: RenderPass) -> Result<&[Attachment], GfxError> {
+        Ok(&self.passes.get(pass)?.color_attachments)
+    }
+
+    fn delete_render_pass(&mut self, pass: RenderPass) -> Result<(), GfxError> {
+        let internal = self.passes.remove(pass)?;
+        self.record(Call::DeleteRenderPass(pass));
+
+        self.delete_attachments(internal.color_attachments.into_iter().chain(internal.depth_attachment))
+    }
+
+    fn new_pipeline_with_params(
+        &mut self,
+        buffer_layout: &[BufferLayout],
+        attributes: &[VertexAttribute],
+        shader: ShaderId,
+        params: PipelineParams,
+    ) -> Result<Pipeline, GfxError> {
+        self.features.check_primitive(params.primitive_type)?;
+        self.features.check_attachments(&params)?;
+        params.check_blend()?;
+        self.capabilities.check_attributes(attributes)?;
+        self.shaders.get(shader)?;
+        check_attributes(buffer_layout, attributes)?;
+
+        let vertex_buffers = attributes.iter().map(|attribute| attribute.buffer_index + 1).max();
+        let pipeline = self.pipelines.insert(RecordedPipeline {
+            shader,
+            vertex_buffers: vertex_buffers.unwrap_or(0),
+        });
+        self.record(Call::NewPipeline { pipeline, shader, params });
+        Ok(pipeline)
+    }
+
+    fn delete_pipeline(&mut self, pipeline: Pipeline) -> Result<(), GfxError> {
+        self.pipelines.remove(pipeline)?;
+        if self.cur_pipeline == Some(pipeline) {
+            self.cur_pipeline = None;
+        }
+        self.record(Call::DeletePipeline(pipeline));
+        Ok(())
+    }
+
+    fn apply_pipeline(&mut self, pipeline: &Pipeline) -> Result<(), GfxError> {
+        let internal = self.pipelines.get(*pipeline)?;
+        self.shaders.get(internal.shader)?;
+
+        if self.cur_pipeline != Some(*pipeline) {
+            self.stats.pipeline_switches += 1;
+        }
+        self.cur_pipeline = Some(*pipeline);
+        self.record(Call::ApplyPipeline(*pipeline));
+        Ok(())
+    }
+
+    fn new_buffer(&mut self, type_: BufferType, usage: BufferUsage, data: BufferSource) -> Result<BufferId, GfxError> {
+        let (size, element_size) = data.size();
+        let index_type = type_.index_type(element_size)?;
+
+        let buffer = self.buffers.insert(RecordedBuffer {
+            buffer_type: type_,
+            size,
+            index_type,
+        });
+        self.record(Call::NewBuffer {
+            buffer,
+            type_,
+            usage,
+            size,
+        });
+        Ok(buffer)
+    }
+
+    fn buffer_update(&mut self, buffer: BufferId, data: BufferSource) -> Result<(), GfxError> {
+        let internal = self.buffers.get(buffer)?;
+        let data = data.update_data(internal.size, internal.index_type)?;
+        self.stats.bytes_uploaded += data.size as u64;
+
+        self.record(Call::BufferUpdate {
+            buffer,
+            bytes: data.as_slice::<u8>().to_vec(),
+        });
+        Ok(())
+    }
+
+    fn buffer_size(&mut self, buffer: BufferId) -> Result<usize, GfxError> {
+        Ok(self.buffers.get(buffer)?.size)
+    }
+
+    fn delete_buffer(&mut self, buffer: BufferId) -> Result<(), GfxError> {
+        self.buffers.remove(buffer)?;
+        if self.index_buffer == Some(buffer) {
+            self.index_buffer = None;
+        }
+        self.record(Call::DeleteBuffer(buffer));
+        Ok(())
+    }
+
+    fn set_label(&mut self, resource: Resource, label: Option<&str>) -> Result<(), GfxError> {
+        check_resource!(self, resource)?;
+
+        self.record(Call::SetLabel {
+            resource,
+            label: label.map(str::to_string),
+        });
+        Ok(())
+    }
+
+    fn apply_viewport(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.record(Call::ApplyViewport { x, y, w, h });
+    }
+
+    fn apply_scissor_rect(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.record(Call::ApplyScissorRect { x, y, w, h });
+    }
+
+    fn apply_bindings(&mut self, bindings: &Bindings) -> Result<(), GfxError> {
+        let pipeline = self.pipelines.get(self.cur_pipeline.ok_or(GfxError::NoPipeline)?)?;
+        let shader = self.shaders.get(pipeline.shader)?;
+        bindings.check_counts(shader.images.len(), pipeline.vertex_buffers)?;
+        for image in &bindings.images {
+            self.textures.get(*image)?;
+        }
+        for buffer in &bindings.vertex_buffers {
+            self.buffers.get(*buffer)?;
+        }
+        if let Some(index_buffer) = bindings.index_buffer {
+            if self.buffers.get(index_buffer)?.buffer_type != BufferType::IndexBuffer {
+                return Err(GfxError::LayoutMismatch(
+                    "Bindings::index_buffer should be created with BufferType::IndexBuffer".to_string(),
+                ));
+            }
+        }
+
+        self.index_buffer = bindings.index_buffer;
+        self.record(Call::ApplyBindings(bindings.clone()));
+        Ok(())
+    }
+
+    fn apply_uniforms_from_bytes(&mut self, uniforms: &[u8], size: usize) -> Result<(), GfxError> {
+        let pipeline = self.pipelines.get(self.cur_pipeline.ok_or(GfxError::NoPipeline)?)?;
+        check_uniforms_size(self.shaders.get(pipeline.shader)?.uniforms.size(), size.min(uniforms.len()))?;
+        self.record(Call::ApplyUniforms(uniforms[..size.min(uniforms.len())].to_vec()));
+        self.stats.uniform_uploads += 1;
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Option<Color>, depth: Option<f32>, stencil: Option<i32>) {
+        self.record(Call::Clear { color, depth, stencil });
+    }
+
+    fn clear_attachment(&mut self, index: usize, value: ClearValue) -> Result<(), GfxError> {
+        self.check_clear_attachment(self.cur_pass, index, value)?;
+        self.record(Call::ClearAttachment { index, value });
+        Ok(())
+    }
+
+    fn begin_default_pass_with_store(&mut self, action: PassAction, store: StoreActions) {
+        self.cur_pass = None;
+        self.record(Call::BeginDefaultPass(action, store));
+    }
+
+    fn begin_pass_with_store(&mut self, pass: RenderPass, action: PassAction, store: StoreActions) -> Result<(), GfxError> {
+        self.check_begin_pass(pass, action)?;
+        self.cur_pass = Some(pass);
+        self.record(Call::BeginPass(pass, action, store));
+        Ok(())
+    }
+
+    fn end_render_pass(&mut self) {
+        self.cur_pass = None;
+        self.index_buffer = None;
+        self.record(Call::EndRenderPass);
+    }
+
+    fn commit_frame(&mut self) {
+        self.index_buffer = None;
+        self.record(Call::CommitFrame);
+        self.frame_starts.push(self.calls.len());
+        self.frame_stats = std::mem::take(&mut self.stats);
+    }
+
+    fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    fn new_query(&mut self, query_type: QueryType) -> Result<QueryId, GfxError> {
+        self.features.check_query(query_type)?;
+        let query = self.queries.insert(RecordedQuery { query_type, begun: false });
+        self.record(Call::NewQuery { query, query_type });
+        Ok(query)
+    }
+
+    fn delete_query(&mut self, query: QueryId) -> Result<(), GfxError> {
+        self.queries.remove(query)?;
+        if self.active_queries.contains(query) {
+            self.active_queries.end(query)?;
+        }
+        self.record(Call::DeleteQuery(query));
+        Ok(())
+    }
+
+    fn begin_query(&mut self, query: QueryId) -> Result<(), GfxError> {
+        let internal = self.queries.get_mut(query)?;
+        self.active_queries.begin(internal.query_type, query)?;
+        internal.begun = true;
+        self.record(Call::BeginQuery(query));
+        Ok(())
+    }
+
+    fn end_query(&mut self, query: QueryId) -> Result<(), GfxError> {
+        self.queries.get(query)?;
+        self.active_queries.end(query)?;
+        self.record(Call::EndQuery(query));
+        Ok(())
+    }
+
+    fn query_result(&mut self, query: QueryId) -> Result<Option<u64>, GfxError> {
+        self.active_queries.check_ended(query, self.queries.get(query)?.begun)?;
+        Ok(Some(0))
+    }
+
+    fn draw(&mut self, first: i32, count: i32, instance_count: i32) {
+        self.assert_pipeline();
+        self.stats.count_draw(count, instance_count);
+        self.record(Call::Draw {
+            first,
+            count,
+            instance_count,
+        });
+    }
+
+    fn draw_indexed(&mut self, first_index: i32, count: i32, instance_count: i32) {
+        self.assert_pipeline();
+        assert!(self.index_buffer.is_some(), "Indexed drawing without any binded index buffer");
+        self.stats.count_draw(count, instance_count);
+        self.record(Call::DrawIndexed {
+            first_index,
+            count,
+            instance_count,
+        });
+    }
+}
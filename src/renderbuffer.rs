@@ -0,0 +1,70 @@
+use crate::error::{GfxError, ResourceKind};
+use crate::resource::{resource_id, Handle};
+use crate::texture::TextureFormat;
+use glow::HasContext;
+
+/// Storage that can be rendered to, but not sampled by shaders.
+/// Used as an attachment of a render pass, see `Attachment::Renderbuffer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderbufferId(pub(crate) Handle);
+resource_id!(RenderbufferId, Renderbuffer);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderbufferParams {
+    pub format: TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    /// Samples per pixel, 1 for a single sampled renderbuffer
+    pub samples: u32,
+}
+
+pub(crate) struct Renderbuffer {
+    pub raw: glow::Renderbuffer,
+    pub params: RenderbufferParams,
+}
+
+impl Renderbuffer {
+    pub fn new(gl: &glow::Context, params: RenderbufferParams) -> Result<Self, GfxError> {
+        unsafe {
+            let raw = gl.create_renderbuffer().map_err(|message| GfxError::ObjectCreation {
+                kind: ResourceKind::Renderbuffer,
+                message,
+            })?;
+            storage(gl, raw, params.format, params.width, params.height, params.samples);
+            Ok(Self { raw, params })
+        }
+    }
+}
+
+/// Allocate the storage of `renderbuffer`, multisampled for more than 1 sample
+pub(crate) unsafe fn storage(
+    gl: &glow::Context,
+    renderbuffer: glow::Renderbuffer,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    samples: u32,
+) {
+    let internal_format = renderbuffer_format(format);
+    gl.bind_renderbuffer(glow::RENDERBUFFER, Some(renderbuffer));
+    if samples > 1 {
+        gl.renderbuffer_storage_multisample(glow::RENDERBUFFER, samples as i32, internal_format, width as i32, height as i32);
+    } else {
+        gl.renderbuffer_storage(glow::RENDERBUFFER, internal_format, width as i32, height as i32);
+    }
+    gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+}
+
+/// Sized internal format of a renderbuffer holding the same data as a texture of `format`
+fn renderbuffer_format(format: TextureFormat) -> u32 {
+    match format {
+        TextureFormat::RGB8 => glow::RGB8,
+        TextureFormat::RGBA8 => glow::RGBA8,
+        TextureFormat::Alpha => glow::R8,
+        TextureFormat::Depth => glow::DEPTH_COMPONENT16,
+        TextureFormat::Depth24Stencil8 => glow::DEPTH24_STENCIL8,
+        TextureFormat::RGBA32F => glow::RGBA32F,
+        TextureFormat::R32I => glow::R32I,
+        TextureFormat::R32UI => glow::R32UI,
+    }
+}
@@ -0,0 +1,182 @@
+use crate::error::{GfxError, ResourceKind};
+use std::marker::PhantomData;
+
+/// Index of a slot in `Resources`, tagged with the generation of the slot
+/// at the time the resource was created.
+///
+/// Deleting a resource bumps the generation of its slot, so handles to the
+/// deleted resource are detected as stale even after the slot is reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Handle {
+    index: u32,
+    generation: u32,
+}
+
+/// Typed handle to a resource stored in `Resources`
+pub(crate) trait ResourceId: Copy {
+    const KIND: ResourceKind;
+
+    fn from_handle(handle: Handle) -> Self;
+    fn handle(self) -> Handle;
+}
+
+macro_rules! resource_id {
+    ($id:ty, $kind:ident) => {
+        impl $crate::resource::ResourceId for $id {
+            const KIND: $crate::error::ResourceKind = $crate::error::ResourceKind::$kind;
+
+            fn from_handle(handle: $crate::resource::Handle) -> Self {
+                Self(handle)
+            }
+
+            fn handle(self) -> $crate::resource::Handle {
+                self.0
+            }
+        }
+    };
+}
+pub(crate) use resource_id;
+
+/// `GfxError::InvalidHandle` unless `$resource`, a `debug::Resource`, is alive in `$backend`.
+/// The backends keep their resources in `Resources` fields named after the kinds.
+macro_rules! check_resource {
+    ($backend:expr, $resource:expr) => {
+        match $resource {
+            $crate::debug::Resource::Buffer(id) => $backend.buffers.get(id).map(drop),
+            $crate::debug::Resource::Texture(id) => $backend.textures.get(id).map(drop),
+            $crate::debug::Resource::Shader(id) => $backend.shaders.get(id).map(drop),
+            $crate::debug::Resource::Pipeline(id) => $backend.pipelines.get(id).map(drop),
+            $crate::debug::Resource::RenderPass(id) => $backend.passes.get(id).map(drop),
+            $crate::debug::Resource::Query(id) => $backend.queries.get(id).map(drop),
+            $crate::debug::Resource::Renderbuffer(id) => $backend.renderbuffers.get(id).map(drop),
+        }
+    };
+}
+pub(crate) use check_resource;
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Generational storage of resources of a single kind.
+/// Slots of deleted resources are reused by the following insertions.
+pub(crate) struct Resources<I, T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    _marker: PhantomData<I>,
+}
+
+impl<I: ResourceId, T> Resources<I, T> {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![],
+            free: vec![],
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> I {
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.slots[index as usize].value = Some(value);
+                index
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                });
+                self.slots.len() as u32 - 1
+            }
+        };
+
+        I::from_handle(Handle {
+            index,
+            generation: self.slots[index as usize].generation,
+        })
+    }
+
+    pub fn get(&self, id: I) -> Result<&T, GfxError> {
+        let Handle { index, generation } = id.handle();
+        match self.slots.get(index as usize) {
+            Some(Slot {
+                generation: g,
+                value: Some(value),
+            }) if *g == generation => Ok(value),
+            _ => Err(GfxError::InvalidHandle(I::KIND)),
+        }
+    }
+
+    pub fn get_mut(&mut self, id: I) -> Result<&mut T, GfxError> {
+        let Handle { index, generation } = id.handle();
+        match self.slots.get_mut(index as usize) {
+            Some(Slot {
+                generation: g,
+                value: Some(value),
+            }) if *g == generation => Ok(value),
+            _ => Err(GfxError::InvalidHandle(I::KIND)),
+        }
+    }
+
+    pub fn contains(&self, id: I) -> bool {
+        self.get(id).is_ok()
+    }
+
+    pub fn remove(&mut self, id: I) -> Result<T, GfxError> {
+        self.get(id)?;
+
+        let index = id.handle().index;
+        let slot = &mut self.slots[index as usize];
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(index);
+        Ok(slot.value.take().unwrap())
+    }
+}
+
+/// Access to a resource that is known to be alive, e.g. one referenced by another live resource
+impl<I: ResourceId, T> std::ops::Index<I> for Resources<I, T> {
+    type Output = T;
+
+    fn index(&self, id: I) -> &T {
+        self.get(id).unwrap_or_else(|err| panic!("{err}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct TestId(Handle);
+    resource_id!(TestId, Buffer);
+
+    #[test]
+    fn stale_handle_after_reuse() {
+        let mut resources = Resources::<TestId, &str>::new();
+        let old = resources.insert("old");
+        assert_eq!(resources.remove(old), Ok("old"));
+
+        let new = resources.insert("new");
+        assert_eq!(new.0.index, old.0.index, "the free slot is reused");
+        assert_eq!(resources.get(new), Ok(&"new"));
+        assert_eq!(resources.get(old), Err(GfxError::InvalidHandle(ResourceKind::Buffer)));
+        assert!(resources.get_mut(old).is_err());
+        assert!(!resources.contains(old));
+    }
+
+    #[test]
+    fn remove_twice() {
+        let mut resources = Resources::<TestId, u32>::new();
+        let a = resources.insert(1);
+        let b = resources.insert(2);
+        assert_eq!(resources.remove(a), Ok(1));
+        assert_eq!(resources.remove(a), Err(GfxError::InvalidHandle(ResourceKind::Buffer)));
+        assert_eq!(resources[b], 2);
+
+        // the double remove must not free the slot again
+        let c = resources.insert(3);
+        let d = resources.insert(4);
+        assert_ne!(c.0.index, d.0.index);
+    }
+}
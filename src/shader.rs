@@ -1,10 +1,13 @@
 use glow::HasContext;
 
+use crate::error::{GfxError, ResourceKind};
+use crate::resource::{resource_id, Handle};
 use crate::uniform::{UniformBlockLayout, UniformType};
 use std::{error::Error, fmt::Display};
 
-#[derive(Clone, Debug, Copy, PartialEq)]
-pub struct ShaderId(pub(crate) usize);
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub struct ShaderId(pub(crate) Handle);
+resource_id!(ShaderId, Shader);
 
 #[derive(Debug)]
 pub(crate) struct ShaderUniform {
@@ -20,19 +23,42 @@ pub(crate) struct ShaderInternal {
 }
 
 impl ShaderInternal {
-    pub fn new(gl: &glow::Context, shader: ShaderSource<'_>, meta: ShaderMeta) -> Result<ShaderInternal, ShaderError> {
+    pub fn new(gl: &glow::Context, shader: ShaderSource<'_>, meta: ShaderMeta) -> Result<ShaderInternal, GfxError> {
         unsafe {
             let vertex_shader = compile_shader(gl, glow::VERTEX_SHADER, shader.vertex)?;
-            let fragment_shader = compile_shader(gl, glow::FRAGMENT_SHADER, shader.fragment)?;
-
-            let program = gl.create_program().unwrap();
+            let fragment_shader = match compile_shader(gl, glow::FRAGMENT_SHADER, shader.fragment) {
+                Ok(fragment_shader) => fragment_shader,
+                Err(err) => {
+                    gl.delete_shader(vertex_shader);
+                    return Err(err);
+                }
+            };
+
+            let program = match gl.create_program() {
+                Ok(program) => program,
+                Err(message) => {
+                    gl.delete_shader(vertex_shader);
+                    gl.delete_shader(fragment_shader);
+                    return Err(GfxError::ObjectCreation {
+                        kind: ResourceKind::Shader,
+                        message,
+                    });
+                }
+            };
             gl.attach_shader(program, vertex_shader);
             gl.attach_shader(program, fragment_shader);
             gl.link_program(program);
 
+            // the linked program keeps everything it needs, shader objects are not used anymore
+            gl.detach_shader(program, vertex_shader);
+            gl.detach_shader(program, fragment_shader);
+            gl.delete_shader(vertex_shader);
+            gl.delete_shader(fragment_shader);
+
             if !gl.get_program_link_status(program) {
                 let error = gl.get_program_info_log(program);
-                return Err(ShaderError::LinkError(error));
+                gl.delete_program(program);
+                return Err(ShaderError::LinkError(error).into());
             }
 
             gl.use_program(Some(program));
@@ -62,15 +88,19 @@ pub(crate) struct ShaderImage {
     pub gl_loc: Option<glow::UniformLocation>,
 }
 
-fn compile_shader(gl: &glow::Context, shader_type: u32, source: &str) -> Result<glow::Shader, ShaderError> {
+fn compile_shader(gl: &glow::Context, shader_type: u32, source: &str) -> Result<glow::Shader, GfxError> {
     unsafe {
-        let shader = gl.create_shader(shader_type).unwrap();
+        let shader = gl.create_shader(shader_type).map_err(|message| GfxError::ObjectCreation {
+            kind: ResourceKind::Shader,
+            message,
+        })?;
 
         gl.shader_source(shader, source);
         gl.compile_shader(shader);
 
         if !gl.get_shader_compile_status(shader) {
             let error_message = gl.get_shader_info_log(shader);
+            gl.delete_shader(shader);
             return Err(ShaderError::CompilationError {
                 shader_type: match shader_type {
                     glow::VERTEX_SHADER => ShaderType::Vertex,
@@ -78,7 +108,8 @@ fn compile_shader(gl: &glow::Context, shader_type: u32, source: &str) -> Result<
                     _ => unreachable!(),
                 },
                 error_message,
-            });
+            }
+            .into());
         }
 
         Ok(shader)
@@ -91,13 +122,13 @@ pub struct ShaderMeta {
     pub images: Vec<String>,
 }
 
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
 pub enum ShaderType {
     Vertex,
     Fragment,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ShaderError {
     CompilationError { shader_type: ShaderType, error_message: String },
     LinkError(String),
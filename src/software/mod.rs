@@ -0,0 +1,1139 @@
+//! CPU reference rasterizer.
+//!
+//! Executes draws without a GPU, with shader stages supplied as Rust closures.
+//! Rendering is fully deterministic, so the output of `texture_read_pixels` and
+//! `SoftwareBackend::framebuffer_pixels` may be compared against golden images.
+
+mod raster;
+mod shader;
+
+pub use shader::{FragmentFn, FragmentInput, FragmentMrtFn, VertexFn, VertexInput, VertexOutput};
+
+use crate::{
+    backend::*, buffer::*, color::*, debug::*, error::*, pass::*, pipeline::*, query::*, renderbuffer::*, resource::*, shader::*,
+    texture::*, *,
+};
+use raster::{ColorTarget, DepthStencilStorage, DepthTarget, RasterState, Surface};
+use shader::Sampler;
+use std::{any::Any, collections::HashSet, rc::Rc};
+
+struct SoftwareShader {
+    vertex: Rc<VertexFn>,
+    fragment: Rc<FragmentMrtFn>,
+}
+
+struct SoftwareTexture {
+    params: TextureParams,
+    bytes: Vec<u8>,
+}
+
+struct SoftwareRenderbuffer {
+    params: RenderbufferParams,
+    bytes: Vec<u8>,
+}
+
+struct SoftwareBuffer {
+    buffer_type: BufferType,
+    index_type: Option<u32>,
+    bytes: Vec<u8>,
+}
+
+/// Location of a single attribute slot in the vertex buffers
+#[derive(Clone, Copy)]
+struct AttributeLayout {
+    buffer_index: usize,
+    offset: usize,
+    stride: usize,
+    format: VertexFormat,
+    divisor: usize,
+}
+
+struct SoftwarePipeline {
+    shader: ShaderId,
+    attributes: Vec<AttributeLayout>,
+    params: PipelineParams,
+}
+
+struct SoftwarePass {
+    color_attachments: Vec<Attachment>,
+    depth_attachment: Option<Attachment>,
+}
+
+struct SoftwareQuery {
+    query_type: QueryType,
+    begun: bool,
+    result: u64,
+}
+
+/// Default framebuffer: RGBA8 color, float depth and 8 bit stencil
+struct Framebuffer {
+    width: i32,
+    height: i32,
+    color: Vec<u8>,
+    depth: Vec<f32>,
+    stencil: Vec<u8>,
+}
+
+pub struct SoftwareBackend {
+    shaders: Resources<ShaderId, SoftwareShader>,
+    pipelines: Resources<Pipeline, SoftwarePipeline>,
+    passes: Resources<RenderPass, SoftwarePass>,
+    buffers: Resources<BufferId, SoftwareBuffer>,
+    textures: Resources<TextureId, SoftwareTexture>,
+    renderbuffers: Resources<RenderbufferId, SoftwareRenderbuffer>,
+    queries: Resources<QueryId, SoftwareQuery>,
+    active_queries: ActiveQueries,
+
+    framebuffer: Framebuffer,
+    /// `None` while rendering to the default framebuffer
+    pass: Option<RenderPass>,
+    viewport: [i32; 4],
+    scissor: [i32; 4],
+    cur_pipeline: Option<Pipeline>,
+    bindings: Option<Bindings>,
+    uniforms: Vec<u8>,
+    /// Color masks of the last applied pipeline, used by clears the same way as glColorMask
+    color_write: [ColorMask; MAX_COLOR_ATTACHMENTS],
+    features: Features,
+    capabilities: Capabilities,
+    /// Counters of the frame in progress
+    stats: FrameStats,
+    frame_stats: FrameStats,
+}
+
+impl Default for SoftwareBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SoftwareBackend {
+    pub fn new() -> Self {
+        Self {
+            shaders: Resources::new(),
+            pipelines: Resources::new(),
+            passes: Resources::new(),
+            buffers: Resources::new(),
+            textures: Resources::new(),
+            renderbuffers: Resources::new(),
+            queries: Resources::new(),
+            active_queries: ActiveQueries::default(),
+            framebuffer: Framebuffer {
+                width: 0,
+                height: 0,
+                color: vec![],
+                depth: vec![],
+                stencil: vec![],
+            },
+            pass: None,
+            viewport: [0; 4],
+            scissor: [0; 4],
+            cur_pipeline: None,
+            bindings: None,
+            uniforms: vec![],
+            color_write: [(true, true, true, true); MAX_COLOR_ATTACHMENTS],
+            features: Features {
+                instancing: true,
+                points: false,
+                loop_and_fan: true,
+                timer_queries: false,
+                sample_count_queries: true,
+                primitive_queries: true,
+                independent_blend: true,
+                framebuffer_invalidation: false,
+            },
+            capabilities: Capabilities {
+                max_texture_size: 16384,
+                max_texture_units: 16,
+                max_vertex_attributes: 16,
+                max_samples: 1,
+                max_color_attachments: MAX_COLOR_ATTACHMENTS,
+                glsl_version: None,
+                extensions: HashSet::new(),
+            },
+            stats: FrameStats::default(),
+            frame_stats: FrameStats::default(),
+        }
+    }
+
+    /// Software counterpart of `new_shader`, with the vertex and fragment stages as closures
+    pub fn new_shader_fn(
+        &mut self,
+        vertex: impl Fn(&VertexInput) -> VertexOutput + 'static,
+        fragment: impl Fn(&FragmentInput) -> [f32; 4] + 'static,
+    ) -> ShaderId {
+        self.new_shader_fn_mrt(vertex, move |input, out| out[0] = fragment(input))
+    }
+
+    /// `new_shader_fn` for render passes with several color attachments.
+    /// `fragment` gets a color per attachment of the pass, all of them initialized to zero.
+    /// Colors of integer attachments are truncated to integers.
+    pub fn new_shader_fn_mrt(
+        &mut self,
+        vertex: impl Fn(&VertexInput) -> VertexOutput + 'static,
+        fragment: impl Fn(&FragmentInput, &mut [[f32; 4]]) + 'static,
+    ) -> ShaderId {
+        self.shaders.insert(SoftwareShader {
+            vertex: Rc::new(vertex),
+            fragment: Rc::new(fragment),
+        })
+    }
+
+    /// RGBA8 content of the default framebuffer, bottom row first, the same as `glReadPixels`
+    pub fn framebuffer_pixels(&self) -> &[u8] {
+        &self.framebuffer.color
+    }
+
+    fn target_size(&self) -> (i32, i32) {
+        match self.pass {
+            Some(pass) => match self.attachment_desc(self.passes[pass].color_attachments[0]) {
+                Ok(desc) => (desc.width as i32, desc.height as i32),
+                Err(_) => (0, 0),
+            },
+            None => (self.framebuffer.width, self.framebuffer.height),
+        }
+    }
+
+    fn begin(&mut self, pass: Option<RenderPass>, action: PassAction) {
+        self.pass = pass;
+        let (width, height) = self.target_size();
+        self.viewport = [0, 0, width, height];
+        self.scissor = [0, 0, width, height];
+
+        if let PassAction::Clear { color, depth, stencil } = action {
+            self.clear_pass(color, depth, stencil);
+        }
+    }
+
+    /// Clear every color attachment to `color`, values that do not match an attachment leave it as it is
+    fn clear_pass(&mut self, color: Option<ClearValue>, depth: Option<f32>, stencil: Option<i32>) {
+        let stencil = stencil.map(|s| s as u8);
+        let (scissor, color_write) = (self.scissor, self.color_write);
+        self.with_surface(|surface, _| surface.clear(scissor, color, &color_write, depth, stencil));
+    }
+
+    /// Run `f` with the render target of the current pass and the textures to sample from.
+    /// The images the pass renders to are copied out of their textures for the time of the call,
+    /// sampling them at the same time gives their contents from before the call, as it is undefined in GL anyway.
+    fn with_surface(&mut self, f: impl FnOnce(&mut Surface, &Resources<TextureId, SoftwareTexture>)) {
+        let Some(pass) = self.pass else {
+            let fb = &mut self.framebuffer;
+            let mut surface = Surface {
+                width: fb.width,
+                height: fb.height,
+                color: vec![ColorTarget {
+                    format: TextureFormat::RGBA8,
+                    bytes: &mut fb.color,
+                }],
+                depth: Some(DepthTarget::Float(&mut fb.depth)),
+                stencil: Some(&mut fb.stencil),
+                samples_passed: 0,
+            };
+            f(&mut surface, &self.textures);
+            return;
+        };
+
+        let SoftwarePass {
+            color_attachments,
+            depth_attachment,
+        } = &self.passes[pass];
+        let (color_attachments, depth_attachment) = (color_attachments.clone(), *depth_attachment);
+        let (width, height) = self.target_size();
+        // like GL, attachments deleted during the pass drop the writes to them: a missing color
+        // attachment draws to scratch memory, a missing depth attachment disables depth and stencil
+        let mut colors: Vec<_> = color_attachments
+            .iter()
+            .map(|attachment| match self.attachment_storage(*attachment) {
+                Some((format, bytes)) => (format, bytes.to_vec()),
+                None => (
+                    TextureFormat::RGBA8,
+                    vec![0; width as usize * height as usize * TextureFormat::RGBA8.pixel_size()],
+                ),
+            })
+            .collect();
+        let mut depth = depth_attachment
+            .and_then(|attachment| self.attachment_storage(attachment))
+            .map(|(format, bytes)| DepthStencilStorage::unpack(format, bytes.to_vec()));
+        let (depth_target, stencil_target) = match depth.as_mut().map(DepthStencilStorage::targets) {
+            Some((depth, stencil)) => (Some(depth), stencil),
+            None => (None, None),
+        };
+
+        let mut surface = Surface {
+            width,
+            height,
+            color: colors
+                .iter_mut()
+                .map(|(format, bytes)| ColorTarget { format: *format, bytes })
+                .collect(),
+            depth: depth_target,
+            stencil: stencil_target,
+            samples_passed: 0,
+        };
+        f(&mut surface, &self.textures);
+
+        for (attachment, (_, bytes)) in color_attachments.into_iter().zip(colors) {
+            if let Some((_, storage)) = self.attachment_storage(attachment) {
+                storage.copy_from_slice(&bytes);
+            }
+        }
+        if let (Some((_, storage)), Some(depth)) = (depth_attachment.and_then(|attachment| self.attachment_storage(attachment)), depth) {
+            storage.copy_from_slice(&depth.pack());
+        }
+    }
+
+    /// Format and bytes of an attachment of the current pass, `None` once it is deleted
+    fn attachment_storage(&mut self, attachment: Attachment) -> Option<(TextureFormat, &mut [u8])> {
+        let (texture, level, layer) = match attachment {
+            Attachment::Texture(texture) => (texture, 0, 0),
+            Attachment::TextureLevel { texture, level, layer } => (texture, level, layer),
+            Attachment::Renderbuffer(renderbuffer) => {
+                let renderbuffer = self.renderbuffers.get_mut(renderbuffer).ok()?;
+                return Some((renderbuffer.params.format, &mut renderbuffer.bytes));
+            }
+        };
+        let texture = self.textures.get_mut(texture).ok()?;
+        let range = texture.params.image_range(level, layer);
+        Some((texture.params.format, &mut texture.bytes[range]))
+    }
+
+    fn draw_elements(&mut self, elements: &[usize], instance_count: i32) {
+        let pipeline = self.cur_pipeline.expect("Drawing without any binded pipeline");
+        let pipeline = &self.pipelines[pipeline];
+        let shader = &self.shaders[pipeline.shader];
+        let (vertex, fragment) = (shader.vertex.clone(), shader.fragment.clone());
+        let (params, attributes) = (pipeline.params, pipeline.attributes.clone());
+
+        let bindings = self.bindings.clone().unwrap_or(Bindings {
+            vertex_buffers: vec![],
+            index_buffer: None,
+            images: vec![],
+        });
+        let (viewport, scissor) = (self.viewport, self.scissor);
+        let uniforms = std::mem::take(&mut self.uniforms);
+        let buffers = &self.buffers;
+
+        let primitives = primitive_count(params.primitive_type, elements.len()) as u64 * instance_count as u64;
+        let mut samples_passed = 0;
+
+        let mut vertices = Vec::with_capacity(elements.len());
+        let mut attribute_values = vec![[0.0; 4]; attributes.len()];
+        let mut outputs = Vec::with_capacity(elements.len() * instance_count as usize);
+        for instance_id in 0..instance_count as usize {
+            vertices.clear();
+            for &vertex_id in elements {
+                for (value, attribute) in attribute_values.iter_mut().zip(&attributes) {
+                    // a buffer deleted after `apply_bindings` reads as zeros, the same as robust GL buffer access
+                    let buffer = bindings
+                        .vertex_buffers
+                        .get(attribute.buffer_index)
+                        .and_then(|b| buffers.get(*b).ok());
+                    let bytes = buffer.map_or(&[][..], |buffer| &buffer.bytes);
+                    *value = fetch_attribute(attribute, bytes, vertex_id, instance_id);
+                }
+                vertices.push(vertex(&VertexInput {
+                    vertex_id,
+                    instance_id,
+                    attributes: &attribute_values,
+                    uniforms: &uniforms,
+                }));
+            }
+            outputs.push(std::mem::take(&mut vertices));
+        }
+
+        self.with_surface(|surface, textures| {
+            let images: Vec<_> = bindings
+                .images
+                .iter()
+                .map(|image| match textures.get(*image) {
+                    Ok(texture) => Sampler {
+                        params: texture.params,
+                        bytes: &texture.bytes,
+                    },
+                    // deleted after `apply_bindings`, an empty texture samples as zeros
+                    Err(_) => Sampler {
+                        params: TextureParams::default(),
+                        bytes: &[],
+                    },
+                })
+                .collect();
+            let state = RasterState {
+                params: &params,
+                viewport,
+                scissor,
+                fragment: &*fragment,
+                uniforms: &uniforms,
+                images: &images,
+            };
+
+            for v in &outputs {
+                let n = v.len();
+                match params.primitive_type {
+                    PrimitiveType::Triangles => v.chunks_exact(3).for_each(|t| surface.draw_triangle(&state, [&t[0], &t[1], &t[2]])),
+                    // every odd triangle is flipped to keep the winding of the strip
+                    PrimitiveType::TriangleStrip => (0..n.saturating_sub(2)).for_each(|i| {
+                        let (a, b) = if i % 2 == 0 { (i, i + 1) } else { (i + 1, i) };
+                        surface.draw_triangle(&state, [&v[a], &v[b], &v[i + 2]])
+                    }),
+                    PrimitiveType::TriangleFan => {
+                        (1..n.saturating_sub(1)).for_each(|i| surface.draw_triangle(&state, [&v[0], &v[i], &v[i + 1]]))
+                    }
+                    PrimitiveType::Lines => v.chunks_exact(2).for_each(|l| surface.draw_line(&state, [&l[0], &l[1]])),
+                    PrimitiveType::LineStrip => v.windows(2).for_each(|l| surface.draw_line(&state, [&l[0], &l[1]])),
+                    PrimitiveType::LineLoop => {
+                        v.windows(2).for_each(|l| surface.draw_line(&state, [&l[0], &l[1]]));
+                        if n > 2 {
+                            surface.draw_line(&state, [&v[n - 1], &v[0]]);
+                        }
+                    }
+                    PrimitiveType::Points => unreachable!("rejected by new_pipeline"),
+                }
+            }
+            samples_passed = surface.samples_passed;
+        });
+
+        self.uniforms = uniforms;
+        for query in self.active_queries.iter() {
+            let query = self.queries.get_mut(query).unwrap();
+            match query.query_type {
+                QueryType::AnySamplesPassed => query.result |= (samples_passed > 0) as u64,
+                QueryType::SamplesPassed => query.result += samples_passed,
+                QueryType::PrimitivesGenerated => query.result += primitives,
+            }
+        }
+    }
+}
+
+/// Number of primitives assembled from `n` vertices, the same way `draw_elements` assembles them
+fn primitive_count(primitive_type: PrimitiveType, n: usize) -> usize {
+    match primitive_type {
+        PrimitiveType::Triangles => n / 3,
+        PrimitiveType::TriangleStrip | PrimitiveType::TriangleFan => n.saturating_sub(2),
+        PrimitiveType::Lines => n / 2,
+        PrimitiveType::LineStrip => n.saturating_sub(1),
+        PrimitiveType::LineLoop if n > 2 => n,
+        PrimitiveType::LineLoop => n.saturating_sub(1),
+        PrimitiveType::Points => n,
+    }
+}
+
+/// Attribute layout of a pipeline, the same as the GL backend computes it,
+/// with attribute slots assigned in order instead of queried from the program
+fn attribute_layout(buffer_layout: &[BufferLayout], attributes: &[VertexAttribute]) -> Vec<AttributeLayout> {
+    let mut strides = vec![0; buffer_layout.len()];
+    for VertexAttribute { format, buffer_index, .. } in attributes {
+        let layout = &buffer_layout[*buffer_index];
+        if layout.stride == 0 {
+            strides[*buffer_index] += format.size_bytes();
+        } else {
+            strides[*buffer_index] = layout.stride;
+        }
+    }
+
+    let mut offsets = vec![0; buffer_layout.len()];
+    let mut res = vec![];
+    for VertexAttribute { format, buffer_index, .. } in attributes {
+        let layout = &buffer_layout[*buffer_index];
+        let divisor = match layout.step_func {
+            VertexStep::PerVertex => 0,
+            _ => layout.step_rate as usize,
+        };
+        let (format, count) = match format {
+            VertexFormat::Mat4 => (VertexFormat::Float4, 4),
+            format => (*format, 1),
+        };
+        for _ in 0..count {
+            res.push(AttributeLayout {
+                buffer_index: *buffer_index,
+                offset: offsets[*buffer_index],
+                stride: strides[*buffer_index] as usize,
+                format,
+                divisor,
+            });
+            offsets[*buffer_index] += format.size_bytes() as usize;
+        }
+    }
+    res
+}
+
+fn fetch_attribute(attribute: &AttributeLayout, bytes: &[u8], vertex_id: usize, instance_id: usize) -> [f32; 4] {
+    let element = match attribute.divisor {
+        0 => vertex_id,
+        divisor => instance_id / divisor,
+    };
+    let start = attribute.offset + element * attribute.stride;
+
+    let mut value = [0.0, 0.0, 0.0, 1.0];
+    let size = (attribute.format.size_bytes() / attribute.format.components()) as usize;
+    for (c, value) in value.iter_mut().take(attribute.format.components() as usize).enumerate() {
+        let Some(b) = bytes.get(start + c * size..start + (c + 1) * size) else {
+            break;
+        };
+        *value = match attribute.format.type_() {
+            glow::FLOAT => f32::from_ne_bytes([b[0], b[1], b[2], b[3]]),
+            glow::UNSIGNED_BYTE => b[0] as f32,
+            glow::UNSIGNED_SHORT => u16::from_ne_bytes([b[0], b[1]]) as f32,
+            _ => u32::from_ne_bytes([b[0], b[1], b[2], b[3]]) as f32,
+        };
+    }
+    value
+}
+
+impl PassResources for SoftwareBackend {
+    fn render_pass_depth_attachment(&self, pass: RenderPass) -> Result<Option<Attachment>, GfxError> {
+        Ok(self.passes.get(pass)?.depth_attachment)
+    }
+}
+
+impl RenderingBackend for SoftwareBackend {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn features(&self) -> &Features {
+        &self.features
+    }
+
+    fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    fn resize(&mut self, width: i32, height: i32) {
+        let size = (width * height) as usize;
+        self.framebuffer = Framebuffer {
+            width,
+            height,
+            color: vec![0; size * 4],
+            depth: vec![1.0; size],
+            stencil: vec![0; size],
+        };
+    }
+
+    fn set_label(&mut self, resource: Resource, _label: Option<&str>) -> Result<(), GfxError> {
+        check_resource!(self, resource)
+    }
+
+    /// GLSL can not be executed on the CPU, use `SoftwareBackend::new_shader_fn` instead
+    fn new_shader(&mut self, _shader: ShaderSource, _meta: ShaderMeta) -> Result<ShaderId, GfxError> {
+        Err(GfxError::Unsupported(
+            "GLSL shaders by SoftwareBackend, use SoftwareBackend::new_shader_fn".to_string(),
+        ))
+    }
+
+    fn delete_shader(&mut self, shader: ShaderId) -> Result<(), GfxError> {
+        self.shaders.remove(shader)?;
+        if let Some(pipeline) = self.cur_pipeline {
+            if self.pipelines.get(pipeline).is_ok_and(|p| p.shader == shader) {
+                self.cur_pipeline = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn new_texture(&mut self, _access: TextureAccess, bytes: Option<&[u8]>, params: TextureParams) -> Result<TextureId, GfxError> {
+        self.capabilities.check_texture_size(params.width, params.height)?;
+        params.check_levels()?;
+        if let Some(bytes) = bytes {
+            params.check_size(params.width, params.height, bytes)?;
+        }
+
+        Ok(self.textures.insert(SoftwareTexture {
+            params,
+            bytes: params.storage(bytes),
+        }))
+    }
+
+    fn delete_texture(&mut self, texture: TextureId) -> Result<(), GfxError> {
+        self.textures.remove(texture)?;
+        Ok(())
+    }
+
+    fn texture_params(&self, texture: TextureId) -> Result<TextureParams, GfxError> {
+        Ok(self.textures.get(texture)?.params)
+    }
+
+    fn texture_set_filter(&mut self, texture: TextureId, filter: FilterMode) -> Result<(), GfxError> {
+        self.textures.get_mut(texture)?.params.filter = filter;
+        Ok(())
+    }
+
+    fn texture_set_wrap(&mut self, texture: TextureId, wrap: TextureWrap) -> Result<(), GfxError> {
+        self.textures.get_mut(texture)?.params.wrap = wrap;
+        Ok(())
+    }
+
+    fn texture_resize(&mut self, texture: TextureId, width: u32, height: u32, bytes: Option<&[u8]>) -> Result<(), GfxError> {
+        self.capabilities.check_texture_size(width, height)?;
+        let t = self.textures.get_mut(texture)?;
+        let params = TextureParams { width, height, ..t.params };
+        params.check_levels()?;
+        if let Some(bytes) = bytes {
+            params.check_size(width, height, bytes)?;
+        }
+        t.params = params;
+        t.bytes = params.storage(bytes);
+        Ok(())
+    }
+
+    fn texture_read_pixels(&mut self, texture: TextureId, bytes: &mut [u8]) -> Result<(), GfxError> {
+        let t = self.textures.get(texture)?;
+        t.params.check_read(bytes)?;
+        let image = &t.bytes[t.params.image_range(0, 0)];
+        bytes[..image.len()].copy_from_slice(image);
+        Ok(())
+    }
+
+    fn texture_update_part(
+        &mut self,
+        texture: TextureId,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        bytes: &[u8],
+    ) -> Result<(), GfxError> {
+        let t = self.textures.get_mut(texture)?;
+        t.params.check_region(x_offset, y_offset, width, height, bytes)?;
+        let format = t.params.format;
+
+        let pixel_size = format.pixel_size();
+        let src_row = width as usize * pixel_size;
+        let dst_row = t.params.width as usize * pixel_size;
+        // a zero width region has no bytes, `chunks_exact` only rejects the zero size
+        for (y, row) in bytes.chunks_exact(src_row.max(1)).enumerate() {
+            let start = (y_offset as usize + y) * dst_row + x_offset as usize * pixel_size;
+            t.bytes[start..start + src_row].copy_from_slice(row);
+        }
+        self.stats.bytes_uploaded += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn new_renderbuffer(&mut self, format: TextureFormat, width: u32, height: u32, samples: u32) -> Result<RenderbufferId, GfxError> {
+        self.capabilities.check_texture_size(width, height)?;
+        self.capabilities.check_samples(samples)?;
+        let params = RenderbufferParams {
+            format,
+            width,
+            height,
+            samples: samples.max(1),
+        };
+        Ok(self.renderbuffers.insert(SoftwareRenderbuffer {
+            params,
+            bytes: vec![0; format.size(width, height)?],
+        }))
+    }
+
+    fn renderbuffer_params(&self, renderbuffer: RenderbufferId) -> Result<RenderbufferParams, GfxError> {
+        Ok(self.renderbuffers.get(renderbuffer)?.params)
+    }
+
+    fn delete_renderbuffer(&mut self, renderbuffer: RenderbufferId) -> Result<(), GfxError> {
+        self.renderbuffers.remove(renderbuffer)?;
+        Ok(())
+    }
+
+    fn new_render_pass_with_attachments(
+        &mut self,
+        color: &[Attachment],
+        depth: Option<Attachment>,
+        samples: u32,
+    ) -> Result<RenderPass, GfxError> {
+        self.check_new_pass(color, depth, samples)?;
+
+        Ok(self.passes.insert(SoftwarePass {
+            color_attachments: color.to_vec(),
+            depth_attachment: depth,
+        }))
+    }
+
+    fn render_pass_color_attachments(&self, pass: RenderPass) -> Result<&[Attachment], GfxError> {
+        Ok(&self.passes.get(pass)?.color_attachments)
+    }
+
+    fn delete_render_pass(&mut self, pass: RenderPass) -> Result<(), GfxError> {
+        let pass = self.passes.remove(pass)?;
+        self.delete_attachments(pass.color_attachments.into_iter().chain(pass.depth_attachment))
+    }
+
+    fn new_pipeline_with_params(
+        &mut self,
+        buffer_layout: &[BufferLayout],
+        attributes: &[VertexAttribute],
+        shader: ShaderId,
+        params: PipelineParams,
+    ) -> Result<Pipeline, GfxError> {
+        self.features.check_primitive(params.primitive_type)?;
+        params.check_blend()?;
+        self.capabilities.check_attributes(attributes)?;
+        self.shaders.get(shader)?;
+        check_attributes(buffer_layout, attributes)?;
+
+        Ok(self.pipelines.insert(SoftwarePipeline {
+            shader,
+            attributes: attribute_layout(buffer_layout, attributes),
+            params,
+        }))
+    }
+
+    fn delete_pipeline(&mut self, pipeline: Pipeline) -> Result<(), GfxError> {
+        self.pipelines.remove(pipeline)?;
+        if self.cur_pipeline == Some(pipeline) {
+            self.cur_pipeline = None;
+        }
+        Ok(())
+    }
+
+    fn apply_pipeline(&mut self, pipeline: &Pipeline) -> Result<(), GfxError> {
+        let internal = self.pipelines.get(*pipeline)?;
+        self.shaders.get(internal.shader)?;
+
+        self.color_write = std::array::from_fn(|n| internal.params.attachment(n).color_write);
+        if self.cur_pipeline != Some(*pipeline) {
+            self.stats.pipeline_switches += 1;
+        }
+        self.cur_pipeline = Some(*pipeline);
+        Ok(())
+    }
+
+    fn new_buffer(&mut self, type_: BufferType, _usage: BufferUsage, data: BufferSource) -> Result<BufferId, GfxError> {
+        let index_type = type_.index_type(data.size().1)?;
+        let bytes = match &data {
+            BufferSource::Slice(data) => data.as_slice::<u8>().to_vec(),
+            BufferSource::Empty { size, .. } => vec![0; *size],
+        };
+
+        Ok(self.buffers.insert(SoftwareBuffer {
+            buffer_type: type_,
+            index_type,
+            bytes,
+        }))
+    }
+
+    fn buffer_update(&mut self, buffer: BufferId, data: BufferSource) -> Result<(), GfxError> {
+        let buffer = self.buffers.get_mut(buffer)?;
+        let data = data.update_data(buffer.bytes.len(), buffer.index_type)?;
+
+        buffer.bytes[..data.size].copy_from_slice(data.as_slice());
+        self.stats.bytes_uploaded += data.size as u64;
+        Ok(())
+    }
+
+    fn buffer_size(&mut self, buffer: BufferId) -> Result<usize, GfxError> {
+        Ok(self.buffers.get(buffer)?.bytes.len())
+    }
+
+    fn delete_buffer(&mut self, buffer: BufferId) -> Result<(), GfxError> {
+        self.buffers.remove(buffer)?;
+        Ok(())
+    }
+
+    fn apply_viewport(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.viewport = [x, y, w, h];
+    }
+
+    fn apply_scissor_rect(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.scissor = [x, y, w, h];
+    }
+
+    fn apply_bindings(&mut self, bindings: &Bindings) -> Result<(), GfxError> {
+        let pipeline = self.pipelines.get(self.cur_pipeline.ok_or(GfxError::NoPipeline)?)?;
+        let vertex_buffers = pipeline.attributes.iter().map(|attribute| attribute.buffer_index + 1).max();
+        // shader closures do not declare the images they sample
+        bindings.check_counts(0, vertex_buffers.unwrap_or(0))?;
+        for image in &bindings.images {
+            self.textures.get(*image)?;
+        }
+        for buffer in &bindings.vertex_buffers {
+            self.buffers.get(*buffer)?;
+        }
+        if let Some(index_buffer) = bindings.index_buffer {
+            if self.buffers.get(index_buffer)?.buffer_type != BufferType::IndexBuffer {
+                return Err(GfxError::LayoutMismatch(
+                    "Bindings::index_buffer should be created with BufferType::IndexBuffer".to_string(),
+                ));
+            }
+        }
+
+        self.bindings = Some(bindings.clone());
+        Ok(())
+    }
+
+    fn apply_uniforms_from_bytes(&mut self, uniforms: &[u8], size: usize) -> Result<(), GfxError> {
+        self.cur_pipeline.ok_or(GfxError::NoPipeline)?;
+        self.uniforms = uniforms[..size.min(uniforms.len())].to_vec();
+        self.stats.uniform_uploads += 1;
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Option<Color>, depth: Option<f32>, stencil: Option<i32>) {
+        self.clear_pass(color.map(ClearValue::from), depth, stencil);
+    }
+
+    fn clear_attachment(&mut self, index: usize, value: ClearValue) -> Result<(), GfxError> {
+        self.check_clear_attachment(self.pass, index, value)?;
+        let (scissor, mask) = (self.scissor, self.color_write[index]);
+        self.with_surface(|surface, _| surface.clear_attachment(scissor, index, value, mask));
+        Ok(())
+    }
+
+    // discarded contents are kept, there is no memory traffic to save
+    fn begin_default_pass_with_store(&mut self, action: PassAction, _store: StoreActions) {
+        self.begin(None, action);
+    }
+
+    fn begin_pass_with_store(&mut self, pass: RenderPass, action: PassAction, _store: StoreActions) -> Result<(), GfxError> {
+        self.check_begin_pass(pass, action)?;
+        self.begin(Some(pass), action);
+        Ok(())
+    }
+
+    fn end_render_pass(&mut self) {
+        self.pass = None;
+        if let Some(bindings) = &mut self.bindings {
+            bindings.index_buffer = None;
+        }
+    }
+
+    fn commit_frame(&mut self) {
+        if let Some(bindings) = &mut self.bindings {
+            bindings.index_buffer = None;
+        }
+        self.frame_stats = std::mem::take(&mut self.stats);
+    }
+
+    fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    fn new_query(&mut self, query_type: QueryType) -> Result<QueryId, GfxError> {
+        self.features.check_query(query_type)?;
+        Ok(self.queries.insert(SoftwareQuery {
+            query_type,
+            begun: false,
+            result: 0,
+        }))
+    }
+
+    fn delete_query(&mut self, query: QueryId) -> Result<(), GfxError> {
+        self.queries.remove(query)?;
+        if self.active_queries.contains(query) {
+            self.active_queries.end(query)?;
+        }
+        Ok(())
+    }
+
+    fn begin_query(&mut self, query: QueryId) -> Result<(), GfxError> {
+        let internal = self.queries.get_mut(query)?;
+        self.active_queries.begin(internal.query_type, query)?;
+        internal.begun = true;
+        internal.result = 0;
+        Ok(())
+    }
+
+    fn end_query(&mut self, query: QueryId) -> Result<(), GfxError> {
+        self.queries.get(query)?;
+        self.active_queries.end(query)
+    }
+
+    /// Draws are executed right away, so results are available as soon as the query is ended
+    fn query_result(&mut self, query: QueryId) -> Result<Option<u64>, GfxError> {
+        let internal = self.queries.get(query)?;
+        self.active_queries.check_ended(query, internal.begun)?;
+        Ok(Some(internal.result))
+    }
+
+    fn draw(&mut self, first: i32, count: i32, instance_count: i32) {
+        let first = first.max(0) as usize;
+        let elements: Vec<_> = (first..first + count.max(0) as usize).collect();
+        self.stats.count_draw(count, instance_count);
+        self.draw_elements(&elements, instance_count);
+    }
+
+    fn draw_indexed(&mut self, first_index: i32, count: i32, instance_count: i32) {
+        let index_buffer = self
+            .bindings
+            .as_ref()
+            .and_then(|b| b.index_buffer)
+            .expect("Indexed drawing without any binded index buffer");
+        // deleted after `apply_bindings`
+        let Ok(buffer) = self.buffers.get(index_buffer) else {
+            return;
+        };
+
+        // indices past the end of the buffer are left out, the same as robust GL buffer access
+        let size = buffer.index_type.unwrap() as usize;
+        let end = ((first_index.max(0) + count.max(0)) as usize * size).min(buffer.bytes.len());
+        let start = (first_index.max(0) as usize * size).min(end);
+        let elements: Vec<_> = buffer.bytes[start..end]
+            .chunks_exact(size)
+            .map(|b| match size {
+                1 => b[0] as usize,
+                2 => u16::from_ne_bytes([b[0], b[1]]) as usize,
+                _ => u32::from_ne_bytes([b[0], b[1], b[2], b[3]]) as usize,
+            })
+            .collect();
+        self.stats.count_draw(count, instance_count);
+        self.draw_elements(&elements, instance_count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4x4 backend drawing with a pipeline that fills with the uniform color
+    fn setup(vertices: &[f32]) -> (SoftwareBackend, Pipeline, BufferId) {
+        let mut ctx = SoftwareBackend::new();
+        ctx.resize(4, 4);
+        let shader = ctx.new_shader_fn(
+            |input| {
+                let [x, y, ..] = input.attributes[0];
+                VertexOutput {
+                    position: [x, y, 0.0, 1.0],
+                    varyings: vec![],
+                }
+            },
+            |input| input.uniforms(),
+        );
+        let attributes = [VertexAttribute::new("pos", VertexFormat::Float2)];
+        let pipeline = ctx.new_pipeline(&[BufferLayout::default()], &attributes, shader).unwrap();
+        let buffer = ctx
+            .new_buffer(BufferType::VertexBuffer, BufferUsage::Immutable, BufferSource::slice(vertices))
+            .unwrap();
+        (ctx, pipeline, buffer)
+    }
+
+    fn draw_red(ctx: &mut SoftwareBackend, pipeline: Pipeline, bindings: Bindings, draw: impl FnOnce(&mut SoftwareBackend)) {
+        ctx.begin_default_pass(PassAction::clear_color(Color::BLACK));
+        ctx.apply_pipeline(&pipeline).unwrap();
+        ctx.apply_bindings(&bindings).unwrap();
+        ctx.apply_uniforms_from_bytes(bytemuck::cast_slice(&[1.0f32, 0.0, 0.0, 1.0]), 16)
+            .unwrap();
+        draw(ctx);
+        ctx.end_render_pass();
+    }
+
+    fn red_pixels(ctx: &SoftwareBackend) -> Vec<bool> {
+        ctx.framebuffer_pixels().chunks(4).map(|c| c == [255, 0, 0, 255]).collect()
+    }
+
+    #[test]
+    fn triangle_covers_pixel_centers() {
+        // lower left half of the framebuffer, the pixel centers with x + y = 3 are on its diagonal edge
+        let (mut ctx, pipeline, buffer) = setup(&[-1.0, -1.0, 1.0, -1.0, -1.0, 1.0]);
+        let bindings = Bindings {
+            vertex_buffers: vec![buffer],
+            index_buffer: None,
+            images: vec![],
+        };
+        draw_red(&mut ctx, pipeline, bindings, |ctx| ctx.draw(0, 3, 1));
+
+        let red = red_pixels(&ctx);
+        for y in 0..4 {
+            for x in 0..4 {
+                // the diagonal is a right edge, the top-left rule leaves its pixels out
+                assert_eq!(red[y * 4 + x], x + y < 3, "pixel {x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn indices_past_the_buffer_are_left_out() {
+        let (mut ctx, pipeline, buffer) = setup(&[-1.0, -1.0, 3.0, -1.0, -1.0, 3.0]);
+        let index_buffer = ctx
+            .new_buffer(BufferType::IndexBuffer, BufferUsage::Immutable, BufferSource::slice(&[0u16, 1, 2]))
+            .unwrap();
+        let bindings = Bindings {
+            vertex_buffers: vec![buffer],
+            index_buffer: Some(index_buffer),
+            images: vec![],
+        };
+        draw_red(&mut ctx, pipeline, bindings, |ctx| {
+            ctx.draw_indexed(0, 6, 1);
+            ctx.draw_indexed(5, 3, 1);
+        });
+        assert!(red_pixels(&ctx).iter().all(|red| *red));
+    }
+
+    #[test]
+    fn resources_deleted_after_apply_bindings() {
+        let (mut ctx, pipeline, buffer) = setup(&[-1.0, -1.0, 3.0, -1.0, -1.0, 3.0]);
+        let index_buffer = ctx
+            .new_buffer(BufferType::IndexBuffer, BufferUsage::Immutable, BufferSource::slice(&[0u16, 1, 2]))
+            .unwrap();
+        let texture = ctx.new_texture_from_rgba8(1, 1, &[255; 4]).unwrap();
+        let bindings = Bindings {
+            vertex_buffers: vec![buffer],
+            index_buffer: Some(index_buffer),
+            images: vec![texture],
+        };
+        draw_red(&mut ctx, pipeline, bindings, |ctx| {
+            ctx.delete_texture(texture).unwrap();
+            ctx.draw(0, 3, 1);
+            ctx.delete_buffer(index_buffer).unwrap();
+            ctx.draw_indexed(0, 3, 1);
+            ctx.delete_buffer(buffer).unwrap();
+            ctx.draw(0, 3, 1);
+        });
+        assert!(red_pixels(&ctx).iter().all(|red| *red));
+    }
+
+    #[test]
+    fn attributes_out_of_the_buffer_layout_are_rejected() {
+        let mut ctx = SoftwareBackend::new();
+        let shader = ctx.new_shader_fn(
+            |_| VertexOutput {
+                position: [0.0; 4],
+                varyings: vec![],
+            },
+            |input| input.uniforms(),
+        );
+        let attributes = [VertexAttribute::with_buffer("pos", VertexFormat::Float2, 1)];
+        let pipeline = ctx.new_pipeline(&[BufferLayout::default()], &attributes, shader);
+        assert!(matches!(pipeline, Err(GfxError::LayoutMismatch(_))));
+    }
+
+    #[test]
+    fn attachments_deleted_during_the_pass() {
+        let mut ctx = SoftwareBackend::new();
+        let params = |format| TextureParams {
+            format,
+            width: 4,
+            height: 4,
+            ..Default::default()
+        };
+        let color = ctx
+            .new_texture(TextureAccess::RenderTarget, None, params(TextureFormat::RGBA8))
+            .unwrap();
+        let depth = ctx
+            .new_texture(TextureAccess::RenderTarget, None, params(TextureFormat::Depth))
+            .unwrap();
+        let pass = ctx.new_render_pass(color, Some(depth)).unwrap();
+
+        ctx.begin_pass(pass, PassAction::Nothing).unwrap();
+        ctx.delete_texture(color).unwrap();
+        ctx.clear(Some(Color::BLACK), Some(1.0), None);
+        ctx.delete_texture(depth).unwrap();
+        ctx.clear(None, Some(1.0), Some(0));
+        ctx.end_render_pass();
+    }
+
+    #[test]
+    fn render_into_a_cube_map_face_level() {
+        let mut ctx = SoftwareBackend::new();
+        let params = TextureParams {
+            width: 8,
+            height: 8,
+            kind: TextureKind::CubeMap,
+            levels: 2,
+            ..Default::default()
+        };
+        let cube = ctx.new_texture(TextureAccess::RenderTarget, None, params).unwrap();
+        let depth = ctx.new_renderbuffer(TextureFormat::Depth, 4, 4, 1).unwrap();
+        let color = Attachment::TextureLevel {
+            texture: cube,
+            level: 1,
+            layer: 3,
+        };
+        let pass = ctx.new_render_pass_with_attachments(&[color], Some(depth.into()), 1).unwrap();
+        ctx.begin_pass(pass, PassAction::clear_color(Color::RED)).unwrap();
+        ctx.end_render_pass();
+
+        let bytes = &ctx.textures[cube].bytes;
+        let face = params.image_range(1, 3);
+        assert_eq!(face.len(), 4 * 4 * 4);
+        assert!(bytes[face.clone()].chunks(4).all(|c| c == [255, 0, 0, 255]));
+        assert!(bytes[..face.start].iter().chain(&bytes[face.end..]).all(|b| *b == 0));
+
+        // level 2 and face 6 do not exist, the 8x8 level 0 does not match the depth
+        let attach = |ctx: &mut SoftwareBackend, level, layer| {
+            let color = Attachment::TextureLevel {
+                texture: cube,
+                level,
+                layer,
+            };
+            ctx.new_render_pass_with_attachments(&[color], Some(depth.into()), 1)
+        };
+        assert!(matches!(attach(&mut ctx, 2, 0), Err(GfxError::OutOfBounds(_))));
+        assert!(matches!(attach(&mut ctx, 1, 6), Err(GfxError::OutOfBounds(_))));
+        assert!(matches!(attach(&mut ctx, 0, 0), Err(GfxError::LayoutMismatch(_))));
+    }
+
+    #[test]
+    fn texture_levels_and_layers() {
+        let mut ctx = SoftwareBackend::new();
+        let mut new_texture = |width, kind, levels| {
+            let params = TextureParams {
+                width,
+                height: 8,
+                kind,
+                levels,
+                ..Default::default()
+            };
+            ctx.new_texture(TextureAccess::Static, None, params)
+        };
+        assert!(new_texture(8, TextureKind::Array(3), 4).is_ok());
+        assert!(new_texture(8, TextureKind::Texture2D, 5).is_err());
+        assert!(new_texture(8, TextureKind::Texture2D, 0).is_err());
+        assert!(new_texture(4, TextureKind::CubeMap, 1).is_err());
+        assert!(new_texture(8, TextureKind::Array(0), 1).is_err());
+
+        // level 0 of each layer is uploaded, one after the other
+        let params = TextureParams {
+            width: 2,
+            height: 2,
+            kind: TextureKind::Array(2),
+            levels: 2,
+            ..Default::default()
+        };
+        let bytes: Vec<u8> = (0..32).collect();
+        assert!(ctx.new_texture(TextureAccess::Static, Some(&bytes[..16]), params).is_err());
+        let texture = ctx.new_texture(TextureAccess::Static, Some(&bytes), params).unwrap();
+        let storage = &ctx.textures[texture].bytes;
+        assert_eq!(storage.len(), 32 + 2 * 4);
+        assert_eq!(storage[params.image_range(0, 1)], bytes[16..]);
+        assert_eq!(params.image_range(1, 1), 36..40);
+    }
+
+    #[test]
+    fn texture_sizes_past_memory_are_rejected() {
+        assert_eq!(TextureFormat::RGBA32F.size(16384, 16384).ok(), (16384usize * 16384).checked_mul(16));
+        assert!(matches!(
+            TextureFormat::RGBA32F.size(u32::MAX, u32::MAX),
+            Err(GfxError::OutOfBounds(ResourceKind::Texture))
+        ));
+        let params = TextureParams {
+            format: TextureFormat::RGBA32F,
+            width: u32::MAX,
+            height: u32::MAX,
+            ..Default::default()
+        };
+        assert!(params.check_levels().is_err());
+    }
+
+    #[test]
+    fn nearest_sampling_picks_the_texel_under_the_coordinate() {
+        let bytes: Vec<u8> = (0..4).flat_map(|n| [n * 10, 0, 0, 255]).collect();
+        let sampler = Sampler {
+            params: TextureParams {
+                wrap: TextureWrap::Repeat,
+                filter: FilterMode::Nearest,
+                width: 4,
+                height: 1,
+                ..Default::default()
+            },
+            bytes: &bytes,
+        };
+        let red = |u: f32| (sampler.sample([u, 0.5])[0] * 255.0).round() as u8;
+        assert_eq!(red(0.0), 0);
+        assert_eq!(red(0.125), 0);
+        assert_eq!(red(0.25), 10);
+        assert_eq!(red(0.99), 30);
+        assert_eq!(red(-0.25), 30);
+        assert_eq!(red(1.0), 0);
+    }
+}
@@ -0,0 +1,567 @@
+use super::shader::{FragmentInput, FragmentMrtFn, Sampler, VertexOutput};
+use crate::{color::Color, pass::ClearValue, pipeline::*, texture::TextureFormat, ColorMask};
+
+pub(crate) struct ColorTarget<'a> {
+    pub format: TextureFormat,
+    pub bytes: &'a mut [u8],
+}
+
+impl<'a> ColorTarget<'a> {
+    fn read(&self, index: usize) -> [f32; 4] {
+        let unorm = |b: u8| b as f32 / 255.0;
+        match self.format {
+            TextureFormat::RGBA8 => {
+                let t = &self.bytes[index * 4..index * 4 + 4];
+                [unorm(t[0]), unorm(t[1]), unorm(t[2]), unorm(t[3])]
+            }
+            TextureFormat::RGB8 => {
+                let t = &self.bytes[index * 3..index * 3 + 3];
+                [unorm(t[0]), unorm(t[1]), unorm(t[2]), 1.0]
+            }
+            // single channel render target, the same as GL_R8
+            TextureFormat::Alpha => [unorm(self.bytes[index]), 0.0, 0.0, 1.0],
+            TextureFormat::RGBA32F => std::array::from_fn(|c| {
+                let at = index * 16 + c * 4;
+                f32::from_ne_bytes(self.bytes[at..at + 4].try_into().unwrap())
+            }),
+            // integer targets are not blended, their value is never read back
+            TextureFormat::R32I | TextureFormat::R32UI => [0.0; 4],
+            TextureFormat::Depth | TextureFormat::Depth24Stencil8 => [0.0; 4],
+        }
+    }
+
+    /// Fragment shader output, converted the same way GL converts it for the format of the target
+    fn write_color(&mut self, index: usize, color: [f32; 4], mask: ColorMask) {
+        let value = match self.format {
+            TextureFormat::R32I => ClearValue::Int(color.map(|c| c as i32)),
+            TextureFormat::R32UI => ClearValue::Uint(color.map(|c| c as u32)),
+            _ => ClearValue::Float(color),
+        };
+        self.write(index, value, mask);
+    }
+
+    /// Values that do not match the format of the target are left out, GL leaves the target undefined for them
+    fn write(&mut self, index: usize, value: ClearValue, (r, g, b, a): ColorMask) {
+        let mask = [r, g, b, a];
+        match (self.format, value) {
+            (TextureFormat::RGBA32F, ClearValue::Float(color)) => {
+                for c in (0..4).filter(|c| mask[*c]) {
+                    let at = index * 16 + c * 4;
+                    self.bytes[at..at + 4].copy_from_slice(&color[c].to_ne_bytes());
+                }
+            }
+            (TextureFormat::R32I, ClearValue::Int([value, ..])) if r => {
+                self.bytes[index * 4..index * 4 + 4].copy_from_slice(&value.to_ne_bytes());
+            }
+            (TextureFormat::R32UI, ClearValue::Uint([value, ..])) if r => {
+                self.bytes[index * 4..index * 4 + 4].copy_from_slice(&value.to_ne_bytes());
+            }
+            (TextureFormat::RGBA8 | TextureFormat::RGB8 | TextureFormat::Alpha, ClearValue::Float(color)) => {
+                let channels = match self.format {
+                    TextureFormat::RGBA8 => 4,
+                    TextureFormat::RGB8 => 3,
+                    _ => 1,
+                };
+                let pixel = &mut self.bytes[index * channels..(index + 1) * channels];
+                for (c, write) in mask[..channels].iter().enumerate() {
+                    if *write {
+                        pixel[c] = (color[c].clamp(0.0, 1.0) * 255.0).round() as u8;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(crate) enum DepthTarget<'a> {
+    Float(&'a mut [f32]),
+    /// `TextureFormat::Depth` texture
+    Unorm16(&'a mut [u8]),
+    /// Depth of a `TextureFormat::Depth24Stencil8` texture, unpacked by `DepthStencilStorage`
+    Unorm24(&'a mut [u32]),
+}
+
+impl<'a> DepthTarget<'a> {
+    fn read(&self, index: usize) -> f32 {
+        match self {
+            DepthTarget::Float(depth) => depth[index],
+            DepthTarget::Unorm16(bytes) => u16::from_ne_bytes([bytes[index * 2], bytes[index * 2 + 1]]) as f32 / 65535.0,
+            DepthTarget::Unorm24(depth) => depth[index] as f32 / UNORM24_MAX,
+        }
+    }
+
+    /// Smallest difference of two depth values that stays distinct in the target,
+    /// the `r` of `glPolygonOffset`
+    fn resolution(&self) -> f32 {
+        match self {
+            // the same as the usual 24 bit depth buffer of a GL default framebuffer
+            DepthTarget::Float(_) => 1.0 / (1 << 24) as f32,
+            DepthTarget::Unorm16(_) => 1.0 / 65535.0,
+            DepthTarget::Unorm24(_) => 1.0 / UNORM24_MAX,
+        }
+    }
+
+    fn write(&mut self, index: usize, value: f32) {
+        match self {
+            DepthTarget::Float(depth) => depth[index] = value,
+            DepthTarget::Unorm16(bytes) => {
+                let value = (value.clamp(0.0, 1.0) * 65535.0).round() as u16;
+                bytes[index * 2..index * 2 + 2].copy_from_slice(&value.to_ne_bytes());
+            }
+            DepthTarget::Unorm24(depth) => depth[index] = (value.clamp(0.0, 1.0) * UNORM24_MAX).round() as u32,
+        }
+    }
+}
+
+const UNORM24_MAX: f32 = ((1 << 24) - 1) as f32;
+
+/// Bytes of the depth attachment of a render pass, copied out of the texture for the time of a draw.
+/// The packed depth and stencil of `TextureFormat::Depth24Stencil8` are split,
+/// so that both of them can be borrowed by the `Surface` at the same time.
+pub(crate) enum DepthStencilStorage {
+    Depth(Vec<u8>),
+    DepthStencil { depth: Vec<u32>, stencil: Vec<u8> },
+}
+
+impl DepthStencilStorage {
+    pub fn unpack(format: TextureFormat, bytes: Vec<u8>) -> Self {
+        match format {
+            TextureFormat::Depth24Stencil8 => {
+                let (depth, stencil) = bytes
+                    .chunks_exact(4)
+                    .map(|t| {
+                        let value = u32::from_ne_bytes([t[0], t[1], t[2], t[3]]);
+                        (value >> 8, value as u8)
+                    })
+                    .unzip();
+                DepthStencilStorage::DepthStencil { depth, stencil }
+            }
+            _ => DepthStencilStorage::Depth(bytes),
+        }
+    }
+
+    pub fn targets(&mut self) -> (DepthTarget<'_>, Option<&mut [u8]>) {
+        match self {
+            DepthStencilStorage::Depth(bytes) => (DepthTarget::Unorm16(bytes), None),
+            DepthStencilStorage::DepthStencil { depth, stencil } => (DepthTarget::Unorm24(depth), Some(stencil)),
+        }
+    }
+
+    /// Bytes to put back into the texture
+    pub fn pack(self) -> Vec<u8> {
+        match self {
+            DepthStencilStorage::Depth(bytes) => bytes,
+            DepthStencilStorage::DepthStencil { depth, stencil } => depth
+                .into_iter()
+                .zip(stencil)
+                .flat_map(|(depth, stencil)| (depth << 8 | stencil as u32).to_ne_bytes())
+                .collect(),
+        }
+    }
+}
+
+/// Render target of the current pass
+pub(crate) struct Surface<'a> {
+    pub width: i32,
+    pub height: i32,
+    /// Color attachments, in the order of the fragment shader outputs
+    pub color: Vec<ColorTarget<'a>>,
+    pub depth: Option<DepthTarget<'a>>,
+    pub stencil: Option<&'a mut [u8]>,
+    /// Fragments that passed the stencil and depth tests, for the occlusion queries
+    pub samples_passed: u64,
+}
+
+/// Everything the rasterizer needs to know about the current draw call
+pub(crate) struct RasterState<'a> {
+    pub params: &'a PipelineParams,
+    /// x, y, width, height
+    pub viewport: [i32; 4],
+    /// x, y, width, height
+    pub scissor: [i32; 4],
+    pub fragment: &'a FragmentMrtFn,
+    pub uniforms: &'a [u8],
+    pub images: &'a [Sampler<'a>],
+}
+
+/// Vertex after perspective division and viewport transform
+struct WindowVertex<'a> {
+    x: f32,
+    y: f32,
+    z: f32,
+    inv_w: f32,
+    varyings: &'a [f32],
+}
+
+impl<'a> Surface<'a> {
+    /// Pixels inside of the surface, the viewport and the scissor, as (x0, y0, x1, y1)
+    fn clip_rect(&self, state: &RasterState) -> (i32, i32, i32, i32) {
+        let [vx, vy, vw, vh] = state.viewport;
+        let [sx, sy, sw, sh] = state.scissor;
+        (
+            vx.max(sx).max(0),
+            vy.max(sy).max(0),
+            (vx + vw).min(sx + sw).min(self.width),
+            (vy + vh).min(sy + sh).min(self.height),
+        )
+    }
+
+    pub fn clear(
+        &mut self,
+        scissor: [i32; 4],
+        color: Option<ClearValue>,
+        color_masks: &[ColorMask],
+        depth: Option<f32>,
+        stencil: Option<u8>,
+    ) {
+        let [sx, sy, sw, sh] = scissor;
+        for y in sy.max(0)..(sy + sh).min(self.height) {
+            for x in sx.max(0)..(sx + sw).min(self.width) {
+                let index = (y * self.width + x) as usize;
+                if let Some(color) = color {
+                    for (target, mask) in self.color.iter_mut().zip(color_masks) {
+                        target.write(index, color, *mask);
+                    }
+                }
+                if let (Some(target), Some(depth)) = (self.depth.as_mut(), depth) {
+                    target.write(index, depth);
+                }
+                if let (Some(target), Some(stencil)) = (self.stencil.as_deref_mut(), stencil) {
+                    target[index] = stencil;
+                }
+            }
+        }
+    }
+
+    /// Clear only the color attachment `n`
+    pub fn clear_attachment(&mut self, scissor: [i32; 4], n: usize, value: ClearValue, mask: ColorMask) {
+        let [sx, sy, sw, sh] = scissor;
+        let width = self.width;
+        let target = &mut self.color[n];
+        for y in sy.max(0)..(sy + sh).min(self.height) {
+            for x in sx.max(0)..(sx + sw).min(width) {
+                target.write((y * width + x) as usize, value, mask);
+            }
+        }
+    }
+
+    pub fn draw_triangle(&mut self, state: &RasterState, vertices: [&VertexOutput; 3]) {
+        let polygon = clip_polygon(vertices.iter().map(|v| (*v).clone()).collect());
+        if polygon.len() < 3 {
+            return;
+        }
+
+        let window: Vec<_> = polygon.iter().map(|v| to_window(v, state.viewport)).collect();
+        for i in 1..window.len() - 1 {
+            self.raster_triangle(state, [&window[0], &window[i], &window[i + 1]]);
+        }
+    }
+
+    pub fn draw_line(&mut self, state: &RasterState, [a, b]: [&VertexOutput; 2]) {
+        let Some((a, b)) = clip_line(a.clone(), b.clone()) else {
+            return;
+        };
+        let (a, b) = (to_window(&a, state.viewport), to_window(&b, state.viewport));
+        let (x0, y0, x1, y1) = self.clip_rect(state);
+
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let x_major = dx.abs() >= dy.abs();
+        let major = if x_major { dx } else { dy };
+        if major == 0.0 {
+            return;
+        }
+        let (p, q) = if major > 0.0 { (&a, &b) } else { (&b, &a) };
+        let (p_major, q_major) = if x_major { (p.x, q.x) } else { (p.y, q.y) };
+        let (p_minor, q_minor) = if x_major { (p.y, q.y) } else { (p.x, q.x) };
+
+        // one fragment per pixel center crossed along the major axis, the last pixel is left out
+        // to not draw shared vertices of line strips twice
+        let mut varyings = vec![0.0; p.varyings.len()];
+        for i in (p_major - 0.5).ceil() as i32..(q_major - 0.5).ceil() as i32 {
+            let t = (i as f32 + 0.5 - p_major) / (q_major - p_major);
+            let minor_coord = p_minor + (q_minor - p_minor) * t;
+            let (x, y) = if x_major {
+                (i, minor_coord.floor() as i32)
+            } else {
+                (minor_coord.floor() as i32, i)
+            };
+            if x < x0 || x >= x1 || y < y0 || y >= y1 {
+                continue;
+            }
+
+            let z = p.z + (q.z - p.z) * t;
+            let weights = [(1.0 - t) * p.inv_w, t * q.inv_w];
+            interpolate(&mut varyings, &[p, q], &weights);
+            self.fragment(state, x, y, z, true, &varyings);
+        }
+    }
+
+    fn raster_triangle(&mut self, state: &RasterState, [a, b, c]: [&WindowVertex; 3]) {
+        let area = edge(a, b, c.x, c.y);
+        if area == 0.0 || !area.is_finite() {
+            return;
+        }
+
+        let ccw = area > 0.0;
+        let front_facing = ccw == (state.params.front_face_order == FrontFaceOrder::CounterClockwise);
+        match state.params.cull_face {
+            CullFace::Front if front_facing => return,
+            CullFace::Back if !front_facing => return,
+            _ => {}
+        }
+
+        // counter clockwise order from now on, so the inside of every edge is positive
+        let (a, b, c) = if ccw { (a, b, c) } else { (a, c, b) };
+        let area = area.abs();
+        let offset = self.polygon_offset(state, [a, b, c], area);
+
+        let (x0, y0, x1, y1) = self.clip_rect(state);
+        let min_x = (a.x.min(b.x).min(c.x).floor() as i32).max(x0);
+        let min_y = (a.y.min(b.y).min(c.y).floor() as i32).max(y0);
+        let max_x = (a.x.max(b.x).max(c.x).ceil() as i32).min(x1);
+        let max_y = (a.y.max(b.y).max(c.y).ceil() as i32).min(y1);
+
+        let mut varyings = vec![0.0; a.varyings.len()];
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let w0 = edge(b, c, px, py);
+                let w1 = edge(c, a, px, py);
+                let w2 = edge(a, b, px, py);
+                if !inside(w0, b, c) || !inside(w1, c, a) || !inside(w2, a, b) {
+                    continue;
+                }
+
+                let (l0, l1, l2) = (w0 / area, w1 / area, w2 / area);
+                let z = l0 * a.z + l1 * b.z + l2 * c.z + offset;
+                let weights = [l0 * a.inv_w, l1 * b.inv_w, l2 * c.inv_w];
+                interpolate(&mut varyings, &[a, b, c], &weights);
+                self.fragment(state, x, y, z, front_facing, &varyings);
+            }
+        }
+    }
+
+    /// Depth offset of every fragment of a counter clockwise triangle, the same as `GL_POLYGON_OFFSET_FILL`
+    fn polygon_offset(&self, state: &RasterState, [a, b, c]: [&WindowVertex; 3], area: f32) -> f32 {
+        let (Some((factor, units)), Some(depth)) = (state.params.depth_write_offset, &self.depth) else {
+            return 0.0;
+        };
+        // depth slope of the triangle in window coordinates
+        let dzdx = ((b.z - a.z) * (c.y - a.y) - (c.z - a.z) * (b.y - a.y)) / area;
+        let dzdy = ((b.x - a.x) * (c.z - a.z) - (c.x - a.x) * (b.z - a.z)) / area;
+        factor * dzdx.abs().max(dzdy.abs()) + units * depth.resolution()
+    }
+
+    /// Per-fragment operations, in the GL order: stencil test, depth test, fragment shader, blending
+    fn fragment(&mut self, state: &RasterState, x: i32, y: i32, z: f32, front_facing: bool, varyings: &[f32]) {
+        let params = state.params;
+        let index = (y * self.width + x) as usize;
+        let z = z.clamp(0.0, 1.0);
+
+        let mut stencil = match (params.stencil_test, self.stencil.as_deref_mut()) {
+            (Some(stencil), Some(target)) => Some((if front_facing { stencil.front } else { stencil.back }, target)),
+            _ => None,
+        };
+
+        if let Some((face, target)) = stencil.as_mut() {
+            let reference = face.test_ref.clamp(0, 255) as u32 & face.test_mask;
+            let value = target[index] as u32 & face.test_mask;
+            if !compare(face.test_func as u32, reference as f32, value as f32) {
+                update_stencil(face, &mut target[index], face.fail_op);
+                return;
+            }
+        }
+
+        // `Comparison::Always` passes the same as a disabled depth test
+        let depth_passed = match &self.depth {
+            Some(target) => compare(params.depth_test as u32, z, target.read(index)),
+            None => true,
+        };
+
+        if let Some((face, target)) = stencil {
+            let op = if depth_passed { face.pass_op } else { face.depth_fail_op };
+            update_stencil(&face, &mut target[index], op);
+        }
+        if !depth_passed {
+            return;
+        }
+        self.samples_passed += 1;
+        if let (Some(target), true) = (self.depth.as_mut(), params.depth_write) {
+            target.write(index, z);
+        }
+
+        let input = FragmentInput {
+            frag_coord: [x as f32 + 0.5, y as f32 + 0.5, z],
+            front_facing,
+            varyings,
+            uniforms: state.uniforms,
+            images: state.images,
+        };
+        let mut colors = [[0.0; 4]; MAX_COLOR_ATTACHMENTS];
+        (state.fragment)(&input, &mut colors[..self.color.len()]);
+
+        for (n, (target, color)) in self.color.iter_mut().zip(colors).enumerate() {
+            let attachment = params.attachment(n);
+            // GL clamps only for normalized targets and never blends integer ones
+            let clamp = |c: f32| match target.format {
+                TextureFormat::RGBA32F => c,
+                _ => c.clamp(0.0, 1.0),
+            };
+            let color = if target.format.is_integer() {
+                color
+            } else {
+                blend(&attachment, params.blend_color, color.map(clamp), target.read(index)).map(clamp)
+            };
+            target.write_color(index, color, attachment.color_write);
+        }
+    }
+}
+
+fn to_window<'a>(v: &'a VertexOutput, [vx, vy, vw, vh]: [i32; 4]) -> WindowVertex<'a> {
+    let [x, y, z, w] = v.position;
+    WindowVertex {
+        x: vx as f32 + (x / w + 1.0) * vw as f32 / 2.0,
+        y: vy as f32 + (y / w + 1.0) * vh as f32 / 2.0,
+        z: (z / w + 1.0) / 2.0,
+        inv_w: 1.0 / w,
+        varyings: &v.varyings,
+    }
+}
+
+fn edge(a: &WindowVertex, b: &WindowVertex, x: f32, y: f32) -> f32 {
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+/// Top-left fill rule, pixels exactly on an edge belong only to one of the triangles sharing it
+fn inside(w: f32, a: &WindowVertex, b: &WindowVertex) -> bool {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    w > 0.0 || (w == 0.0 && (dy < 0.0 || (dy == 0.0 && dx < 0.0)))
+}
+
+/// Perspective-correct interpolation, `weights` are barycentric coordinates divided by w
+fn interpolate(out: &mut [f32], vertices: &[&WindowVertex], weights: &[f32]) {
+    let sum: f32 = weights.iter().sum();
+    for (i, out) in out.iter_mut().enumerate() {
+        *out = vertices.iter().zip(weights).map(|(v, w)| v.varyings[i] * w).sum::<f32>() / sum;
+    }
+}
+
+fn lerp(a: &VertexOutput, b: &VertexOutput, t: f32) -> VertexOutput {
+    VertexOutput {
+        position: std::array::from_fn(|i| a.position[i] + (b.position[i] - a.position[i]) * t),
+        varyings: a.varyings.iter().zip(&b.varyings).map(|(a, b)| a + (b - a) * t).collect(),
+    }
+}
+
+/// Distances to the near and far clip planes, positive inside
+fn clip_distances(v: &VertexOutput) -> [f32; 2] {
+    let [_, _, z, w] = v.position;
+    [z + w, w - z]
+}
+
+/// Clip against near and far planes. Clipping against the other planes
+/// is left to the clip rectangle of the rasterizer.
+fn clip_polygon(mut polygon: Vec<VertexOutput>) -> Vec<VertexOutput> {
+    for plane in 0..2 {
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for i in 0..polygon.len() {
+            let (a, b) = (&polygon[i], &polygon[(i + 1) % polygon.len()]);
+            let (da, db) = (clip_distances(a)[plane], clip_distances(b)[plane]);
+            if da >= 0.0 {
+                clipped.push(a.clone());
+            }
+            if (da >= 0.0) != (db >= 0.0) {
+                clipped.push(lerp(a, b, da / (da - db)));
+            }
+        }
+        polygon = clipped;
+    }
+    polygon
+}
+
+fn clip_line(mut a: VertexOutput, mut b: VertexOutput) -> Option<(VertexOutput, VertexOutput)> {
+    for plane in 0..2 {
+        let (da, db) = (clip_distances(&a)[plane], clip_distances(&b)[plane]);
+        match (da >= 0.0, db >= 0.0) {
+            (true, true) => {}
+            (false, false) => return None,
+            (true, false) => b = lerp(&a, &b, da / (da - db)),
+            (false, true) => a = lerp(&a, &b, da / (da - db)),
+        }
+    }
+    Some((a, b))
+}
+
+/// `func` is one of the GL comparison functions, the discriminant of both `Comparison` and `CompareFunc`
+fn compare(func: u32, a: f32, b: f32) -> bool {
+    match func {
+        glow::NEVER => false,
+        glow::LESS => a < b,
+        glow::LEQUAL => a <= b,
+        glow::GREATER => a > b,
+        glow::GEQUAL => a >= b,
+        glow::EQUAL => a == b,
+        glow::NOTEQUAL => a != b,
+        _ => true,
+    }
+}
+
+fn update_stencil(face: &StencilFaceState, value: &mut u8, op: StencilOp) {
+    let reference = face.test_ref.clamp(0, 255) as u8;
+    let new = match op {
+        StencilOp::Keep => *value,
+        StencilOp::Zero => 0,
+        StencilOp::Replace => reference,
+        StencilOp::IncrementClamp => value.saturating_add(1),
+        StencilOp::DecrementClamp => value.saturating_sub(1),
+        StencilOp::Invert => !*value,
+        StencilOp::IncrementWrap => value.wrapping_add(1),
+        StencilOp::DecrementWrap => value.wrapping_sub(1),
+    };
+    let write_mask = face.write_mask as u8;
+    *value = (*value & !write_mask) | (new & write_mask);
+}
+
+fn blend(attachment: &AttachmentParams, blend_color: Color, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    let Some(color_blend) = attachment.color_blend else {
+        return src;
+    };
+    let alpha_blend = attachment.alpha_blend.unwrap_or(color_blend);
+    let Color { r, g, b, a } = blend_color;
+    let constant = [r, g, b, a].map(|c| c as f32 / 255.0);
+
+    std::array::from_fn(|c| {
+        let state = if c == 3 { alpha_blend } else { color_blend };
+        let s = src[c] * blend_factor(state.sfactor, c, src, dst, constant);
+        let d = dst[c] * blend_factor(state.dfactor, c, src, dst, constant);
+        match state.equation {
+            Equation::Add => s + d,
+            Equation::Subtract => s - d,
+            Equation::ReverseSubtract => d - s,
+            Equation::Min => src[c].min(dst[c]),
+            Equation::Max => src[c].max(dst[c]),
+        }
+    })
+}
+
+fn blend_factor(factor: BlendFactor, c: usize, src: [f32; 4], dst: [f32; 4], constant: [f32; 4]) -> f32 {
+    let value = |value| match value {
+        BlendValue::SourceColor => src[c],
+        BlendValue::SourceAlpha => src[3],
+        BlendValue::DestinationColor => dst[c],
+        BlendValue::DestinationAlpha => dst[3],
+    };
+    match factor {
+        BlendFactor::Zero => 0.0,
+        BlendFactor::One => 1.0,
+        BlendFactor::Value(v) => value(v),
+        BlendFactor::OneMinusValue(v) => 1.0 - value(v),
+        BlendFactor::SourceAlphaSaturate if c == 3 => 1.0,
+        BlendFactor::ConstantColor => constant[c],
+        BlendFactor::OneMinusConstantColor => 1.0 - constant[c],
+        BlendFactor::ConstantAlpha => constant[3],
+        BlendFactor::OneMinusConstantAlpha => 1.0 - constant[3],
+        BlendFactor::SourceAlphaSaturate => src[3].min(1.0 - dst[3]),
+    }
+}
@@ -0,0 +1,179 @@
+use crate::texture::{FilterMode, TextureFormat, TextureParams, TextureWrap};
+
+/// Vertex stage of a software shader
+pub type VertexFn = dyn Fn(&VertexInput) -> VertexOutput;
+/// Fragment stage of a software shader, returns RGBA color of the fragment
+pub type FragmentFn = dyn Fn(&FragmentInput) -> [f32; 4];
+/// Fragment stage writing to several color attachments, RGBA color `n` goes to the attachment `n`
+pub type FragmentMrtFn = dyn Fn(&FragmentInput, &mut [[f32; 4]]);
+
+/// Values available to the vertex stage for a single vertex
+pub struct VertexInput<'a> {
+    /// Index of the vertex, for indexed draws it is the value read from the index buffer
+    pub vertex_id: usize,
+    pub instance_id: usize,
+    pub(crate) attributes: &'a [[f32; 4]],
+    pub(crate) uniforms: &'a [u8],
+}
+
+impl<'a> VertexInput<'a> {
+    /// Attribute `n`, in the order of the `VertexAttribute`s of the pipeline.
+    /// `VertexFormat::Mat4` attributes take 4 consecutive slots, one per column.
+    /// Missing components are filled from `(0, 0, 0, 1)`, as in GLSL.
+    pub fn attribute(&self, n: usize) -> [f32; 4] {
+        self.attributes[n]
+    }
+
+    /// Uniforms applied with `apply_uniforms`, read as the same `#[repr(C)]` struct
+    pub fn uniforms<T: bytemuck::Pod>(&self) -> T {
+        read_uniforms(self.uniforms)
+    }
+}
+
+/// Result of the vertex stage
+#[derive(Clone, Debug)]
+pub struct VertexOutput {
+    /// Clip space position, the same as `gl_Position`
+    pub position: [f32; 4],
+    /// Values interpolated across the primitive and passed to the fragment stage
+    pub varyings: Vec<f32>,
+}
+
+/// Values available to the fragment stage for a single fragment
+pub struct FragmentInput<'a> {
+    /// Window coordinates of the fragment center and its depth, the same as `gl_FragCoord.xyz`
+    pub frag_coord: [f32; 3],
+    pub front_facing: bool,
+    pub(crate) varyings: &'a [f32],
+    pub(crate) uniforms: &'a [u8],
+    pub(crate) images: &'a [Sampler<'a>],
+}
+
+impl<'a> FragmentInput<'a> {
+    /// Perspective-correct interpolation of the `VertexOutput::varyings`
+    pub fn varyings(&self) -> &[f32] {
+        self.varyings
+    }
+
+    /// Uniforms applied with `apply_uniforms`, read as the same `#[repr(C)]` struct
+    pub fn uniforms<T: bytemuck::Pod>(&self) -> T {
+        read_uniforms(self.uniforms)
+    }
+
+    /// Sample image `n` of the bindings at normalized coordinates `uv`,
+    /// using the filter and wrap mode of the texture
+    pub fn sample(&self, n: usize, uv: [f32; 2]) -> [f32; 4] {
+        self.images[n].sample(uv)
+    }
+}
+
+fn read_uniforms<T: bytemuck::Pod>(uniforms: &[u8]) -> T {
+    let size = std::mem::size_of::<T>();
+    assert!(size <= uniforms.len(), "Uniforms struct does not match applied uniforms");
+    bytemuck::pod_read_unaligned(&uniforms[..size])
+}
+
+pub(crate) struct Sampler<'a> {
+    pub params: TextureParams,
+    pub bytes: &'a [u8],
+}
+
+impl<'a> Sampler<'a> {
+    pub fn sample(&self, [u, v]: [f32; 2]) -> [f32; 4] {
+        let (w, h) = (self.params.width as f32, self.params.height as f32);
+
+        match self.params.filter {
+            FilterMode::Nearest => self.texel((u * w).floor() as i64, (v * h).floor() as i64),
+            FilterMode::Linear => {
+                let (x, y) = (u * w - 0.5, v * h - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let t00 = self.texel(x0, y0);
+                let t10 = self.texel(x0 + 1, y0);
+                let t01 = self.texel(x0, y0 + 1);
+                let t11 = self.texel(x0 + 1, y0 + 1);
+
+                let mut res = [0.0; 4];
+                for i in 0..4 {
+                    let bottom = t00[i] + (t10[i] - t00[i]) * fx;
+                    let top = t01[i] + (t11[i] - t01[i]) * fx;
+                    res[i] = bottom + (top - bottom) * fy;
+                }
+                res
+            }
+        }
+    }
+
+    fn texel(&self, x: i64, y: i64) -> [f32; 4] {
+        let (w, h) = (self.params.width as i64, self.params.height as i64);
+        if w == 0 || h == 0 {
+            return [0.0; 4];
+        }
+        let x = wrap(x, w, self.params.wrap) as usize;
+        let y = wrap(y, h, self.params.wrap) as usize;
+
+        let index = y * w as usize + x;
+        read_texel(self.params.format, self.bytes, index).unwrap_or_default()
+    }
+}
+
+fn wrap(coord: i64, size: i64, wrap: TextureWrap) -> i64 {
+    match wrap {
+        TextureWrap::Repeat => coord.rem_euclid(size),
+        TextureWrap::Mirror => {
+            let period = coord.rem_euclid(2 * size);
+            if period < size {
+                period
+            } else {
+                2 * size - 1 - period
+            }
+        }
+        TextureWrap::Clamp => coord.clamp(0, size - 1),
+    }
+}
+
+/// Texel `index` of a texture of `format` as RGBA, the same way GL samples it.
+/// `None` when `index` is past the end of `bytes`.
+pub(crate) fn read_texel(format: TextureFormat, bytes: &[u8], index: usize) -> Option<[f32; 4]> {
+    let unorm = |b: u8| b as f32 / 255.0;
+    let texel = match format {
+        TextureFormat::RGB8 => {
+            let t = bytes.get(index * 3..index * 3 + 3)?;
+            [unorm(t[0]), unorm(t[1]), unorm(t[2]), 1.0]
+        }
+        TextureFormat::RGBA8 => {
+            let t = bytes.get(index * 4..index * 4 + 4)?;
+            [unorm(t[0]), unorm(t[1]), unorm(t[2]), unorm(t[3])]
+        }
+        TextureFormat::Depth => {
+            let t = bytes.get(index * 2..index * 2 + 2)?;
+            [u16::from_ne_bytes([t[0], t[1]]) as f32 / 65535.0, 0.0, 0.0, 1.0]
+        }
+        TextureFormat::Depth24Stencil8 => {
+            let t = bytes.get(index * 4..index * 4 + 4)?;
+            let depth = u32::from_ne_bytes([t[0], t[1], t[2], t[3]]) >> 8;
+            [depth as f32 / ((1 << 24) - 1) as f32, 0.0, 0.0, 1.0]
+        }
+        TextureFormat::RGBA32F => {
+            let t = bytes.get(index * 16..index * 16 + 16)?;
+            std::array::from_fn(|c| f32::from_ne_bytes(t[c * 4..c * 4 + 4].try_into().unwrap()))
+        }
+        // the value an integer sampler returns, converted to float
+        TextureFormat::R32I => {
+            let t = bytes.get(index * 4..index * 4 + 4)?;
+            [i32::from_ne_bytes(t.try_into().unwrap()) as f32, 0.0, 0.0, 1.0]
+        }
+        TextureFormat::R32UI => {
+            let t = bytes.get(index * 4..index * 4 + 4)?;
+            [u32::from_ne_bytes(t.try_into().unwrap()) as f32, 0.0, 0.0, 1.0]
+        }
+        // matches the Red -> Alpha swizzle of the GL backend
+        TextureFormat::Alpha => {
+            let a = unorm(*bytes.get(index)?);
+            [a, 0.0, 0.0, a]
+        }
+    };
+    Some(texel)
+}
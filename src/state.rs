@@ -1,43 +1,93 @@
-use crate::{buffer::*, cache::*, color::*, pass::*, pipeline::*, shader::*, texture::*, uniform::*, *};
+use crate::{
+    backend::*, buffer::*, cache::*, color::*, debug::*, error::*, pass::*, pipeline::*, query::*, renderbuffer::*, resource::*, shader::*,
+    texture::*, timer::*, uniform::*, *,
+};
 use glow::HasContext;
+use std::any::Any;
 
-pub struct Features {
-    pub instancing: bool,
-}
-
-pub struct QuadContext {
+/// OpenGL 3 / GLES 3 / WebGL 2 backend
+pub struct GlBackend {
     pub(crate) gl: glow::Context,
-    shaders: Vec<ShaderInternal>,
-    pipelines: Vec<PipelineInternal>,
-    passes: Vec<RenderPassInternal>,
-    buffers: Vec<Buffer>,
-    textures: Vec<Texture>,
+    shaders: Resources<ShaderId, ShaderInternal>,
+    pipelines: Resources<Pipeline, PipelineInternal>,
+    passes: Resources<RenderPass, RenderPassInternal>,
+    buffers: Resources<BufferId, Buffer>,
+    textures: Resources<TextureId, Texture>,
+    queries: Resources<QueryId, QueryInternal>,
+    renderbuffers: Resources<RenderbufferId, Renderbuffer>,
+    active_queries: ActiveQueries,
     default_framebuffer: Option<glow::Framebuffer>,
+    /// Offscreen pass in progress, resolved by `end_render_pass` when it is multisampled
+    cur_pass: Option<RenderPass>,
+    /// Store actions of the pass in progress, applied by `end_render_pass`
+    cur_store: StoreActions,
     pub(crate) cache: GlCache,
 
     pub(crate) features: Features,
+    capabilities: Capabilities,
     width: i32,
     height: i32,
+    /// `KHR_debug` output is enabled, pipelines open debug groups
+    debug_output: bool,
+    /// Loaded by `GlBackend::from_loader`, for contexts with `GL_ARB_debug_output` but no `KHR_debug`
+    #[cfg(not(target_arch = "wasm32"))]
+    debug_message_callback_arb: Option<DebugMessageCallbackArb>,
+    /// A debug group named after the current pipeline is open
+    debug_group: bool,
+    frame_stats: FrameStats,
+    timers: GpuTimers,
 }
 
-impl QuadContext {
+impl GlBackend {
     pub fn new(gl: glow::Context) -> Self {
         unsafe {
             let default_framebuffer = convert_framebuffer(gl.get_parameter_i32(glow::FRAMEBUFFER_BINDING));
             let vao = gl.create_vertex_array().ok();
             gl.bind_vertex_array(vao);
 
-            let instancing = gl.version().major >= 3;
+            let version = gl.version();
+            let instancing = version.major >= 3;
+            let sample_count_queries = !version.is_embedded;
+            let primitive_queries = !version.is_embedded || (version.major, version.minor) >= (3, 2);
+            let extensions = gl.supported_extensions();
+            let independent_blend = (version.major, version.minor) >= if version.is_embedded { (3, 2) } else { (4, 0) }
+                || extensions.contains("GL_ARB_draw_buffers_blend")
+                || extensions.contains("GL_OES_draw_buffers_indexed")
+                || extensions.contains("OES_draw_buffers_indexed");
+            let framebuffer_invalidation = (version.major, version.minor) >= if version.is_embedded { (3, 0) } else { (4, 3) }
+                || extensions.contains("GL_ARB_invalidate_subdata");
+            let timers = GpuTimers::new(&gl);
+            let capabilities = Capabilities::from_gl(&gl);
+
+            // Desktop GL ignores gl_PointSize unless it is explicitly enabled,
+            // GLES and WebGL always use it
+            if !gl.version().is_embedded {
+                gl.enable(glow::PROGRAM_POINT_SIZE);
+            }
 
             Self {
                 gl,
                 default_framebuffer,
-                shaders: vec![],
-                pipelines: vec![],
-                passes: vec![],
-                buffers: vec![],
-                textures: vec![],
-                features: Features { instancing },
+                cur_pass: None,
+                cur_store: StoreActions::default(),
+                shaders: Resources::new(),
+                pipelines: Resources::new(),
+                passes: Resources::new(),
+                buffers: Resources::new(),
+                textures: Resources::new(),
+                queries: Resources::new(),
+                renderbuffers: Resources::new(),
+                active_queries: ActiveQueries::default(),
+                features: Features {
+                    instancing,
+                    points: true,
+                    loop_and_fan: true,
+                    timer_queries: timers.supported,
+                    sample_count_queries,
+                    primitive_queries,
+                    independent_blend,
+                    framebuffer_invalidation,
+                },
                 cache: GlCache {
                     stored_index_buffer: None,
                     stored_index_type: None,
@@ -46,32 +96,102 @@ impl QuadContext {
                     index_type: None,
                     vertex_buffer: None,
                     cur_pipeline: None,
-                    color_blend: None,
-                    alpha_blend: None,
+                    attachments: [AttachmentParams {
+                        color_write: (true, true, true, true),
+                        color_blend: None,
+                        alpha_blend: None,
+                    }; MAX_COLOR_ATTACHMENTS],
+                    blend_color: Color::default(),
                     stencil: None,
-                    color_write: (true, true, true, true),
                     cull_face: CullFace::Nothing,
-                    stored_texture: None,
-                    textures: [None; MAX_SHADERSTAGE_IMAGES],
-                    attributes: [None; MAX_VERTEX_ATTRIBUTES],
+                    depth_test: None,
+                    depth_write: true,
+                    polygon_offset: None,
+                    stored_texture: (glow::TEXTURE_2D, None),
+                    textures: vec![(glow::TEXTURE_2D, None); capabilities.max_texture_units],
+                    attributes: vec![None; capabilities.max_vertex_attributes],
+                    stats: FrameStats::default(),
                 },
+                capabilities,
                 width: 0,
                 height: 0,
+                debug_output: false,
+                #[cfg(not(target_arch = "wasm32"))]
+                debug_message_callback_arb: None,
+                debug_group: false,
+                frame_stats: FrameStats::default(),
+                timers,
             }
         }
     }
 
-    unsafe fn set_blend(&mut self, color_blend: Option<BlendState>, alpha_blend: Option<BlendState>) {
-        if color_blend.is_none() && alpha_blend.is_some() {
-            panic!("AlphaBlend without ColorBlend");
+    /// `new` with the context loaded through `loader`, which also makes the `GL_ARB_debug_output`
+    /// fallback of `enable_debug_output` available
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_loader(mut loader: impl FnMut(&str) -> *const std::ffi::c_void) -> Self {
+        let gl = unsafe { glow::Context::from_loader_function(&mut loader) };
+        let mut backend = Self::new(gl);
+        let callback = loader("glDebugMessageCallbackARB");
+        if !callback.is_null() {
+            backend.debug_message_callback_arb =
+                Some(unsafe { std::mem::transmute::<*const std::ffi::c_void, DebugMessageCallbackArb>(callback) });
+        }
+        backend
+    }
+
+    /// Color write masks, blending and the blend color of the color attachments
+    unsafe fn set_attachments(&mut self, params: &PipelineParams) {
+        if params.uniform_attachments() {
+            let AttachmentParams {
+                color_write,
+                color_blend,
+                alpha_blend,
+            } = params.attachment(0);
+            self.set_blend(None, color_blend, alpha_blend);
+            self.set_color_write(None, color_write);
+        } else {
+            for n in 0..self.capabilities.max_color_attachments.min(MAX_COLOR_ATTACHMENTS) {
+                let attachment = params.attachment(n);
+                self.set_blend(Some(n), attachment.color_blend, attachment.alpha_blend);
+                self.set_color_write(Some(n), attachment.color_write);
+            }
+        }
+
+        // the blend color only matters while blending, it is left as is otherwise
+        let blending = (0..MAX_COLOR_ATTACHMENTS).any(|n| params.attachment(n).color_blend.is_some());
+        if blending && self.cache.blend_color != params.blend_color {
+            let Color { r, g, b, a } = params.blend_color;
+            self.gl
+                .blend_color(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0);
+            self.cache.blend_color = params.blend_color;
         }
-        if self.cache.color_blend == color_blend && self.cache.alpha_blend == alpha_blend {
+    }
+
+    /// Blending of the color attachment `draw_buffer`, or of all of them for `None`
+    /// `alpha_blend` without `color_blend` is rejected by `new_pipeline_with_params`.
+    unsafe fn set_blend(&mut self, draw_buffer: Option<usize>, color_blend: Option<BlendState>, alpha_blend: Option<BlendState>) {
+        let cached = match draw_buffer {
+            Some(n) => &mut self.cache.attachments[n..n + 1],
+            None => &mut self.cache.attachments[..],
+        };
+        if cached
+            .iter()
+            .all(|cached| cached.color_blend == color_blend && cached.alpha_blend == alpha_blend)
+        {
             return;
         }
+        let enabled = cached.iter().all(|cached| cached.color_blend.is_some());
+        for cached in cached {
+            cached.color_blend = color_blend;
+            cached.alpha_blend = alpha_blend;
+        }
 
         if let Some(color_blend) = color_blend {
-            if self.cache.color_blend.is_none() {
-                self.gl.enable(glow::BLEND);
+            if !enabled {
+                match draw_buffer {
+                    Some(n) => self.gl.enable_draw_buffer(glow::BLEND, n as u32),
+                    None => self.gl.enable(glow::BLEND),
+                }
             }
 
             let BlendState {
@@ -79,26 +199,31 @@ impl QuadContext {
                 sfactor: src_rgb,
                 dfactor: dst_rgb,
             } = color_blend;
-
-            if let Some(BlendState {
+            let BlendState {
                 equation: eq_alpha,
                 sfactor: src_alpha,
                 dfactor: dst_alpha,
-            }) = alpha_blend
-            {
-                self.gl
-                    .blend_func_separate(src_rgb.into(), dst_rgb.into(), src_alpha.into(), dst_alpha.into());
-                self.gl.blend_equation_separate(eq_rgb as _, eq_alpha as _);
-            } else {
-                self.gl.blend_func(src_rgb.into(), dst_rgb.into());
-                self.gl.blend_equation_separate(eq_rgb as _, eq_rgb as _);
+            } = alpha_blend.unwrap_or(color_blend);
+
+            match draw_buffer {
+                Some(n) => {
+                    let n = n as u32;
+                    self.gl
+                        .blend_func_separate_draw_buffer(n, src_rgb.into(), dst_rgb.into(), src_alpha.into(), dst_alpha.into());
+                    self.gl.blend_equation_separate_draw_buffer(n, eq_rgb as _, eq_alpha as _);
+                }
+                None => {
+                    self.gl
+                        .blend_func_separate(src_rgb.into(), dst_rgb.into(), src_alpha.into(), dst_alpha.into());
+                    self.gl.blend_equation_separate(eq_rgb as _, eq_alpha as _);
+                }
+            }
+        } else {
+            match draw_buffer {
+                Some(n) => self.gl.disable_draw_buffer(glow::BLEND, n as u32),
+                None => self.gl.disable(glow::BLEND),
             }
-        } else if self.cache.color_blend.is_some() {
-            self.gl.disable(glow::BLEND);
         }
-
-        self.cache.color_blend = color_blend;
-        self.cache.alpha_blend = alpha_blend;
     }
 
     unsafe fn set_stencil(&mut self, stencil_test: Option<StencilState>) {
@@ -150,184 +275,423 @@ impl QuadContext {
         self.cache.cull_face = cull_face;
     }
 
-    unsafe fn set_color_write(&mut self, color_write: ColorMask) {
-        if self.cache.color_write == color_write {
+    unsafe fn set_depth(&mut self, depth_test: Comparison, depth_write: bool) {
+        // GL does not write depth with DEPTH_TEST disabled, so it stays enabled for `Always` with writes
+        let depth_test = (depth_test != Comparison::Always || depth_write).then_some(depth_test);
+        if self.cache.depth_test != depth_test {
+            match depth_test {
+                Some(depth_test) => {
+                    if self.cache.depth_test.is_none() {
+                        self.gl.enable(glow::DEPTH_TEST);
+                    }
+                    self.gl.depth_func(depth_test as u32);
+                }
+                None => self.gl.disable(glow::DEPTH_TEST),
+            }
+            self.cache.depth_test = depth_test;
+        }
+        self.set_depth_write(depth_write);
+    }
+
+    unsafe fn set_depth_write(&mut self, depth_write: bool) {
+        if self.cache.depth_write != depth_write {
+            self.gl.depth_mask(depth_write);
+            self.cache.depth_write = depth_write;
+        }
+    }
+
+    unsafe fn set_polygon_offset(&mut self, polygon_offset: Option<(f32, f32)>) {
+        if self.cache.polygon_offset == polygon_offset {
             return;
         }
+
+        if let Some((factor, units)) = polygon_offset {
+            if self.cache.polygon_offset.is_none() {
+                self.gl.enable(glow::POLYGON_OFFSET_FILL);
+            }
+            self.gl.polygon_offset(factor, units);
+        } else {
+            self.gl.disable(glow::POLYGON_OFFSET_FILL);
+        }
+        self.cache.polygon_offset = polygon_offset;
+    }
+
+    /// Clear the `colors` color attachments and the depth and stencil of the pass just begun, as `action` asks.
+    /// The clear values are checked against the attachments already.
+    fn apply_pass_action(&mut self, action: PassAction, colors: usize) {
+        if let PassAction::Clear { color, depth, stencil } = action {
+            if let Some(value) = color {
+                for index in 0..colors {
+                    unsafe { self.clear_buffer(index, value) }
+                }
+            }
+            self.clear(None, depth, stencil);
+        }
+    }
+
+    /// `glClearBuffer` of color attachment `index`, the variant has to match the attachment format
+    unsafe fn clear_buffer(&self, index: usize, value: ClearValue) {
+        match value {
+            ClearValue::Float(value) => self.gl.clear_buffer_f32_slice(glow::COLOR, index as u32, &value),
+            ClearValue::Int(value) => self.gl.clear_buffer_i32_slice(glow::COLOR, index as u32, &value),
+            ClearValue::Uint(value) => self.gl.clear_buffer_u32_slice(glow::COLOR, index as u32, &value),
+        }
+    }
+
+    /// Discard the buffers of the default framebuffer according to `store`
+    unsafe fn invalidate_default_framebuffer(&self, store: StoreActions) {
+        // the window framebuffer names its buffers differently from a framebuffer object
+        let (color, depth, stencil) = match self.default_framebuffer {
+            None => (glow::COLOR, glow::DEPTH, glow::STENCIL),
+            Some(_) => (glow::COLOR_ATTACHMENT0, glow::DEPTH_ATTACHMENT, glow::STENCIL_ATTACHMENT),
+        };
+        let attachments: Vec<_> = [(store.color, color), (store.depth, depth), (store.stencil, stencil)]
+            .into_iter()
+            .filter(|(action, _)| *action == StoreAction::Discard)
+            .map(|(_, attachment)| attachment)
+            .collect();
+        if !attachments.is_empty() {
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, self.default_framebuffer);
+            self.gl.invalidate_framebuffer(glow::FRAMEBUFFER, &attachments);
+        }
+    }
+
+    unsafe fn pop_debug_group(&mut self) {
+        if self.debug_group {
+            self.gl.pop_debug_group();
+            self.debug_group = false;
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    unsafe fn label_object(&self, resource: Resource, label: Option<&str>) {
+        let (identifier, name) = match resource {
+            Resource::Buffer(buffer) => match self.buffers[buffer].gl_buf {
+                Some(gl_buf) => (glow::BUFFER, gl_buf.0),
+                None => return,
+            },
+            Resource::Texture(texture) => match self.textures[texture].raw {
+                Some(raw) => (glow::TEXTURE, raw.0),
+                None => return,
+            },
+            Resource::Shader(shader) => (glow::PROGRAM, self.shaders[shader].program.0),
+            Resource::RenderPass(pass) => (glow::FRAMEBUFFER, self.passes[pass].gl_fb.0),
+            Resource::Renderbuffer(renderbuffer) => (glow::RENDERBUFFER, self.renderbuffers[renderbuffer].raw.0),
+            Resource::Query(query) => match &self.queries[query] {
+                internal if internal.begun => (glow::QUERY, internal.raw.0),
+                // applied by the first begin_query
+                _ => return,
+            },
+            // there is no GL object behind a pipeline, its label is used for debug groups instead
+            Resource::Pipeline(_) => return,
+        };
+        self.gl.object_label(identifier, name.get(), label);
+    }
+
+    // WebGL has no KHR_debug
+    #[cfg(target_arch = "wasm32")]
+    unsafe fn label_object(&self, _resource: Resource, _label: Option<&str>) {}
+
+    /// Color write mask of the color attachment `draw_buffer`, or of all of them for `None`
+    unsafe fn set_color_write(&mut self, draw_buffer: Option<usize>, color_write: ColorMask) {
+        let cached = match draw_buffer {
+            Some(n) => &mut self.cache.attachments[n..n + 1],
+            None => &mut self.cache.attachments[..],
+        };
+        if cached.iter().all(|cached| cached.color_write == color_write) {
+            return;
+        }
+        for cached in cached {
+            cached.color_write = color_write;
+        }
+
         let (r, g, b, a) = color_write;
-        self.gl.color_mask(r, g, b, a);
-        self.cache.color_write = color_write;
+        match draw_buffer {
+            Some(n) => self.gl.color_mask_draw_buffer(n as u32, r, g, b, a),
+            None => self.gl.color_mask(r, g, b, a),
+        }
     }
 }
 
-impl QuadContext {
-    pub fn resize(&mut self, width: i32, height: i32) {
+impl PassResources for GlBackend {
+    fn render_pass_depth_attachment(&self, pass: RenderPass) -> Result<Option<Attachment>, GfxError> {
+        Ok(self.passes.get(pass)?.depth_attachment)
+    }
+}
+
+impl RenderingBackend for GlBackend {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn resize(&mut self, width: i32, height: i32) {
         self.width = width;
         self.height = height;
     }
 
-    pub fn new_shader(&mut self, shader: ShaderSource, meta: ShaderMeta) -> Result<ShaderId, ShaderError> {
-        let shader = ShaderInternal::new(&self.gl, shader, meta)?;
-        self.shaders.push(shader);
-        Ok(ShaderId(self.shaders.len() - 1))
+    fn features(&self) -> &Features {
+        &self.features
     }
 
-    pub fn new_texture(&mut self, access: TextureAccess, bytes: Option<&[u8]>, params: TextureParams) -> TextureId {
-        let texture = Texture::new(self, access, bytes, params);
-        self.textures.push(texture);
-        TextureId(self.textures.len() - 1)
+    fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
     }
 
-    fn new_texture_from_data_and_format(&mut self, bytes: &[u8], params: TextureParams) -> TextureId {
-        self.new_texture(TextureAccess::Static, Some(bytes), params)
+    fn enable_debug_output(&mut self, callback: DebugCallback) -> bool {
+        if self.gl.supports_debug() {
+            *DEBUG_CALLBACK.lock().unwrap_or_else(|err| err.into_inner()) = Some(callback);
+            unsafe {
+                self.gl.enable(glow::DEBUG_OUTPUT);
+                // report messages from the thread and the call that caused them
+                self.gl.enable(glow::DEBUG_OUTPUT_SYNCHRONOUS);
+                self.gl.debug_message_callback(dispatch);
+            }
+            self.debug_output = true;
+            return true;
+        }
+
+        // the older extension has the messages, but neither object labels nor debug groups,
+        // and its output is always on in a debug context
+        #[cfg(not(target_arch = "wasm32"))]
+        if let (true, Some(debug_message_callback)) = (
+            self.gl.supported_extensions().contains("GL_ARB_debug_output"),
+            self.debug_message_callback_arb,
+        ) {
+            *DEBUG_CALLBACK.lock().unwrap_or_else(|err| err.into_inner()) = Some(callback);
+            unsafe {
+                // GL_DEBUG_OUTPUT_SYNCHRONOUS_ARB has the same value
+                self.gl.enable(glow::DEBUG_OUTPUT_SYNCHRONOUS);
+                debug_message_callback(Some(arb_callback), std::ptr::null());
+            }
+            return true;
+        }
+        false
     }
 
-    pub fn delete_texture(&mut self, texture: TextureId) {
-        let t = &mut self.textures[texture.0];
-        unsafe { self.gl.delete_texture(t.raw.take().unwrap()) }
+    fn set_label(&mut self, resource: Resource, label: Option<&str>) -> Result<(), GfxError> {
+        check_resource!(self, resource)?;
+        match resource {
+            Resource::Pipeline(pipeline) => self.pipelines.get_mut(pipeline)?.label = label.map(str::to_string),
+            Resource::Query(query) => self.queries.get_mut(query)?.label = label.map(str::to_string),
+            _ => {}
+        }
+
+        if self.gl.supports_debug() {
+            unsafe { self.label_object(resource, label) }
+        }
+        Ok(())
     }
 
-    pub fn texture_size(&self, texture: TextureId) -> (u32, u32) {
-        let params = self.texture_params(texture);
-        (params.width, params.height)
+    fn new_shader(&mut self, shader: ShaderSource, meta: ShaderMeta) -> Result<ShaderId, GfxError> {
+        self.capabilities.check_images(meta.images.len())?;
+        let shader = ShaderInternal::new(&self.gl, shader, meta)?;
+        Ok(self.shaders.insert(shader))
     }
 
-    pub fn texture_set_filter(&mut self, texture: TextureId, filter: FilterMode) {
-        let t = self.textures[texture.0];
-        t.set_filter(self, filter);
+    fn delete_shader(&mut self, shader: ShaderId) -> Result<(), GfxError> {
+        let internal = self.shaders.remove(shader)?;
+
+        if let Some(pipeline) = self.cache.cur_pipeline {
+            if self.pipelines.get(pipeline).is_ok_and(|p| p.shader == shader) {
+                self.cache.cur_pipeline = None;
+                unsafe { self.gl.use_program(None) }
+            }
+        }
+
+        unsafe { self.gl.delete_program(internal.program) }
+        Ok(())
     }
 
-    pub fn texture_set_wrap(&mut self, texture: TextureId, wrap: TextureWrap) {
-        let t = self.textures[texture.0];
-        t.set_wrap(self, wrap);
+    fn new_texture(&mut self, access: TextureAccess, bytes: Option<&[u8]>, params: TextureParams) -> Result<TextureId, GfxError> {
+        self.capabilities.check_texture_size(params.width, params.height)?;
+        let texture = Texture::new(self, access, bytes, params)?;
+        Ok(self.textures.insert(texture))
     }
 
-    pub fn texture_resize(&mut self, texture: TextureId, width: u32, height: u32, bytes: Option<&[u8]>) {
-        let mut t = self.textures[texture.0];
-        t.resize(self, width, height, bytes);
+    fn delete_texture(&mut self, texture: TextureId) -> Result<(), GfxError> {
+        let t = self.textures.remove(texture)?;
+        if let Some(raw) = t.raw {
+            unsafe { self.gl.delete_texture(raw) }
+        }
+        Ok(())
     }
 
-    pub fn texture_read_pixels(&mut self, texture: TextureId, bytes: &mut [u8]) {
-        let t = self.textures[texture.0];
-        t.read_pixels(self, bytes);
+    fn texture_set_filter(&mut self, texture: TextureId, filter: FilterMode) -> Result<(), GfxError> {
+        let t = *self.textures.get(texture)?;
+        t.set_filter(self, filter);
+        Ok(())
     }
 
-    /// Update whole texture content
-    /// bytes should be width * height * 4 size - non rgba8 textures are not supported yet anyway
-    pub fn texture_update(&mut self, texture: TextureId, bytes: &[u8]) {
-        let (width, height) = self.texture_size(texture);
-        self.texture_update_part(texture, 0 as _, 0 as _, width as _, height as _, bytes)
+    fn texture_set_wrap(&mut self, texture: TextureId, wrap: TextureWrap) -> Result<(), GfxError> {
+        let t = *self.textures.get(texture)?;
+        t.set_wrap(self, wrap);
+        Ok(())
     }
 
-    pub fn texture_update_part(&mut self, texture: TextureId, x_offset: i32, y_offset: i32, width: i32, height: i32, bytes: &[u8]) {
-        let t = self.textures[texture.0];
-        t.update_texture_part(self, x_offset, y_offset, width, height, bytes);
+    fn texture_resize(&mut self, texture: TextureId, width: u32, height: u32, bytes: Option<&[u8]>) -> Result<(), GfxError> {
+        let mut t = *self.textures.get(texture)?;
+        self.capabilities.check_texture_size(width, height)?;
+        t.resize(self, width, height, bytes)?;
+        *self.textures.get_mut(texture)? = t;
+        Ok(())
     }
 
-    pub fn new_texture_from_rgba8(&mut self, width: u16, height: u16, bytes: &[u8]) -> TextureId {
-        assert_eq!(width as usize * height as usize * 4, bytes.len());
+    fn texture_read_pixels(&mut self, texture: TextureId, bytes: &mut [u8]) -> Result<(), GfxError> {
+        let t = *self.textures.get(texture)?;
+        t.read_pixels(self, bytes)
+    }
 
-        self.new_texture_from_data_and_format(
-            bytes,
-            TextureParams {
-                width: width as _,
-                height: height as _,
-                format: TextureFormat::RGBA8,
-                wrap: TextureWrap::Clamp,
-                filter: FilterMode::Nearest,
-            },
-        )
+    fn texture_update_part(
+        &mut self,
+        texture: TextureId,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        bytes: &[u8],
+    ) -> Result<(), GfxError> {
+        let t = *self.textures.get(texture)?;
+        t.update_texture_part(self, x_offset, y_offset, width, height, bytes)?;
+        self.cache.stats.bytes_uploaded += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn texture_params(&self, texture: TextureId) -> Result<TextureParams, GfxError> {
+        Ok(self.textures.get(texture)?.params)
     }
 
-    pub fn texture_params(&self, texture: TextureId) -> TextureParams {
-        let texture = self.textures[texture.0];
-        texture.params
+    fn new_renderbuffer(&mut self, format: TextureFormat, width: u32, height: u32, samples: u32) -> Result<RenderbufferId, GfxError> {
+        self.capabilities.check_texture_size(width, height)?;
+        self.capabilities.check_samples(samples)?;
+        let params = RenderbufferParams {
+            format,
+            width,
+            height,
+            samples: samples.max(1),
+        };
+        let renderbuffer = Renderbuffer::new(&self.gl, params)?;
+        Ok(self.renderbuffers.insert(renderbuffer))
     }
 
-    pub fn new_render_pass(&mut self, color_img: TextureId, depth_img: Option<TextureId>) -> RenderPass {
-        let pass = RenderPassInternal::new(&self.gl, &self.textures, self.default_framebuffer, color_img, depth_img);
-        self.passes.push(pass);
-        RenderPass(self.passes.len() - 1)
+    fn renderbuffer_params(&self, renderbuffer: RenderbufferId) -> Result<RenderbufferParams, GfxError> {
+        Ok(self.renderbuffers.get(renderbuffer)?.params)
     }
 
-    pub fn render_pass_texture(&self, pass: RenderPass) -> TextureId {
-        self.passes[pass.0].texture
+    fn delete_renderbuffer(&mut self, renderbuffer: RenderbufferId) -> Result<(), GfxError> {
+        let renderbuffer = self.renderbuffers.remove(renderbuffer)?;
+        unsafe { self.gl.delete_renderbuffer(renderbuffer.raw) }
+        Ok(())
     }
 
-    pub fn delete_render_pass(&mut self, pass: RenderPass) {
-        unsafe { self.gl.delete_framebuffer(self.passes[pass.0].gl_fb.take().unwrap()) }
+    fn new_render_pass_with_attachments(
+        &mut self,
+        color: &[Attachment],
+        depth: Option<Attachment>,
+        samples: u32,
+    ) -> Result<RenderPass, GfxError> {
+        self.check_new_pass(color, depth, samples)?;
+
+        let pass = RenderPassInternal::new(
+            &self.gl,
+            &self.textures,
+            &self.renderbuffers,
+            self.default_framebuffer,
+            color,
+            depth,
+            samples,
+        )?;
+        Ok(self.passes.insert(pass))
+    }
 
-        self.delete_texture(self.passes[pass.0].texture);
-        if let Some(depth_texture) = self.passes[pass.0].depth_texture {
-            self.delete_texture(depth_texture);
-        }
+    fn render_pass_color_attachments(&self, pass: RenderPass) -> Result<&[Attachment], GfxError> {
+        Ok(&self.passes.get(pass)?.color_attachments)
     }
 
-    pub fn new_pipeline(&mut self, buffer_layout: &[BufferLayout], attributes: &[VertexAttribute], shader: ShaderId) -> Pipeline {
-        self.new_pipeline_with_params(buffer_layout, attributes, shader, Default::default())
+    fn delete_render_pass(&mut self, pass: RenderPass) -> Result<(), GfxError> {
+        let pass = self.passes.remove(pass)?;
+        unsafe { pass.delete(&self.gl) }
+
+        self.delete_attachments(pass.color_attachments.into_iter().chain(pass.depth_attachment))
     }
 
-    pub fn new_pipeline_with_params(
+    fn new_pipeline_with_params(
         &mut self,
         buffer_layout: &[BufferLayout],
         attributes: &[VertexAttribute],
         shader: ShaderId,
         params: PipelineParams,
-    ) -> Pipeline {
-        let pipeline = PipelineInternal::new(&self.gl, buffer_layout, attributes, shader, self.shaders[shader.0].program, params);
-        self.pipelines.push(pipeline);
-        Pipeline(self.pipelines.len() - 1)
+    ) -> Result<Pipeline, GfxError> {
+        self.features.check_primitive(params.primitive_type)?;
+        self.features.check_attachments(&params)?;
+        params.check_blend()?;
+        self.capabilities.check_attributes(attributes)?;
+
+        let program = self.shaders.get(shader)?.program;
+        let pipeline = PipelineInternal::new(&self.gl, buffer_layout, attributes, shader, program, params)?;
+        Ok(self.pipelines.insert(pipeline))
     }
 
-    pub fn apply_pipeline(&mut self, pipeline: &Pipeline) {
+    fn delete_pipeline(&mut self, pipeline: Pipeline) -> Result<(), GfxError> {
+        self.pipelines.remove(pipeline)?;
+
+        if self.cache.cur_pipeline == Some(pipeline) {
+            self.cache.cur_pipeline = None;
+        }
+        Ok(())
+    }
+
+    fn apply_pipeline(&mut self, pipeline: &Pipeline) -> Result<(), GfxError> {
+        let internal = self.pipelines.get(*pipeline)?;
+        // the shader could be deleted after the pipeline was created
+        let program = self.shaders.get(internal.shader)?.program;
+        let params = internal.params;
+        if self.cache.cur_pipeline != Some(*pipeline) {
+            self.cache.stats.pipeline_switches += 1;
+        }
         self.cache.cur_pipeline = Some(*pipeline);
 
         unsafe {
-            let internal = &self.pipelines[pipeline.0];
-            let shader = &mut self.shaders[internal.shader.0];
+            self.pop_debug_group();
+            if let (true, Some(label)) = (self.debug_output, &self.pipelines[*pipeline].label) {
+                self.gl.push_debug_group(glow::DEBUG_SOURCE_APPLICATION, 0, label);
+                self.debug_group = true;
+            }
 
-            self.gl.use_program(Some(shader.program));
+            self.gl.use_program(Some(program));
             self.gl.enable(glow::SCISSOR_TEST);
 
-            if internal.params.depth_write {
-                self.gl.enable(glow::DEPTH_TEST);
-                self.gl.depth_func(internal.params.depth_test as u32)
-            } else {
-                self.gl.disable(glow::DEPTH_TEST);
-            }
+            self.set_depth(params.depth_test, params.depth_write);
 
-            match internal.params.front_face_order {
+            match params.front_face_order {
                 FrontFaceOrder::Clockwise => self.gl.front_face(glow::CW),
                 FrontFaceOrder::CounterClockwise => self.gl.front_face(glow::CCW),
             }
 
-            self.set_cull_face(self.pipelines[pipeline.0].params.cull_face);
-            self.set_blend(
-                self.pipelines[pipeline.0].params.color_blend,
-                self.pipelines[pipeline.0].params.alpha_blend,
-            );
-
-            self.set_stencil(self.pipelines[pipeline.0].params.stencil_test);
-            self.set_color_write(self.pipelines[pipeline.0].params.color_write);
+            self.set_cull_face(params.cull_face);
+            self.set_attachments(&params);
+            self.set_stencil(params.stencil_test);
+            self.set_polygon_offset(params.depth_write_offset);
         }
+        Ok(())
     }
 
-    pub fn new_buffer(&mut self, type_: BufferType, usage: BufferUsage, data: BufferSource) -> BufferId {
+    fn new_buffer(&mut self, type_: BufferType, usage: BufferUsage, data: BufferSource) -> Result<BufferId, GfxError> {
         let gl_target = type_ as u32;
         let gl_usage = usage as u32;
-        let (size, element_size) = match &data {
-            BufferSource::Slice(data) => (data.size, data.element_size),
-            BufferSource::Empty { size, element_size } => (*size, *element_size),
-        };
-        let index_type = match type_ {
-            BufferType::IndexBuffer if element_size == 1 || element_size == 2 || element_size == 4 => Some(element_size as u32),
-            BufferType::IndexBuffer => panic!("unsupported index buffer dimension"),
-            BufferType::VertexBuffer => None,
-        };
-        let gl_buf;
+        let (size, element_size) = data.size();
+        let index_type = type_.index_type(element_size)?;
+
+        let gl_buf = unsafe { self.gl.create_buffer() }.map_err(|message| GfxError::ObjectCreation {
+            kind: ResourceKind::Buffer,
+            message,
+        })?;
+        let gl_buf = Some(gl_buf);
 
         unsafe {
-            gl_buf = self.gl.create_buffer().ok();
             self.cache.store_buffer_binding(gl_target);
             self.cache.bind_buffer(&self.gl, gl_target, gl_buf, index_type);
 
@@ -345,88 +709,86 @@ impl QuadContext {
             size,
             index_type,
         };
-        self.buffers.push(buffer);
-        BufferId(self.buffers.len() - 1)
+        Ok(self.buffers.insert(buffer))
     }
 
-    pub fn buffer_update(&mut self, buffer: BufferId, data: BufferSource) {
-        let data = match data {
-            BufferSource::Slice(data) => data,
-            _ => panic!("buffer_update expects BufferSource::slice"),
-        };
+    fn buffer_update(&mut self, buffer: BufferId, data: BufferSource) -> Result<(), GfxError> {
+        let buffer = self.buffers.get(buffer)?;
+        let data = data.update_data(buffer.size, buffer.index_type)?;
         debug_assert!(data.is_slice);
-        let buffer = &self.buffers[buffer.0];
-
-        if matches!(buffer.buffer_type, BufferType::IndexBuffer) {
-            assert!(buffer.index_type.is_some());
-            assert!(data.element_size as u32 == buffer.index_type.unwrap());
-        };
-
-        let size = data.size;
-
-        assert!(size <= buffer.size);
 
         let gl_target = buffer.buffer_type as u32;
         self.cache.store_buffer_binding(gl_target);
         self.cache.bind_buffer(&self.gl, gl_target, buffer.gl_buf, buffer.index_type);
         unsafe { self.gl.buffer_sub_data_u8_slice(gl_target, 0, data.as_slice()) };
         self.cache.restore_buffer_binding(&self.gl, gl_target);
+        self.cache.stats.bytes_uploaded += data.size as u64;
+        Ok(())
     }
 
-    /// Size of buffer in bytes
-    pub fn buffer_size(&mut self, buffer: BufferId) -> usize {
-        self.buffers[buffer.0].size
+    fn buffer_size(&mut self, buffer: BufferId) -> Result<usize, GfxError> {
+        Ok(self.buffers.get(buffer)?.size)
     }
 
-    /// Delete GPU buffer, leaving handle unmodified.
-    ///
-    /// There is no protection against using deleted textures later. However its not an UB in OpenGl and thats why
-    /// this function is not marked as unsafe
-    pub fn delete_buffer(&mut self, buffer: BufferId) {
-        unsafe { self.gl.delete_buffer(self.buffers[buffer.0].gl_buf.take().unwrap()) }
+    fn delete_buffer(&mut self, buffer: BufferId) -> Result<(), GfxError> {
+        let buffer = self.buffers.remove(buffer)?;
+        if let Some(gl_buf) = buffer.gl_buf {
+            unsafe { self.gl.delete_buffer(gl_buf) }
+        }
         self.cache.clear_buffer_bindings(&self.gl);
         self.cache.clear_vertex_attributes();
+        Ok(())
     }
 
-    /// Set a new viewport rectangle.
-    /// Should be applied after begin_pass.
-    pub fn apply_viewport(&mut self, x: i32, y: i32, w: i32, h: i32) {
+    fn apply_viewport(&mut self, x: i32, y: i32, w: i32, h: i32) {
         unsafe { self.gl.viewport(x, y, w, h) }
     }
 
-    /// Set a new scissor rectangle.
-    /// Should be applied after begin_pass.
-    pub fn apply_scissor_rect(&mut self, x: i32, y: i32, w: i32, h: i32) {
+    fn apply_scissor_rect(&mut self, x: i32, y: i32, w: i32, h: i32) {
         unsafe { self.gl.scissor(x, y, w, h) }
     }
 
-    pub fn apply_bindings(&mut self, bindings: &Bindings) {
-        let pip = &self.pipelines[self.cache.cur_pipeline.unwrap().0];
-        let shader = &self.shaders[pip.shader.0];
+    fn apply_bindings(&mut self, bindings: &Bindings) -> Result<(), GfxError> {
+        let pip = self.pipelines.get(self.cache.cur_pipeline.ok_or(GfxError::NoPipeline)?)?;
+        let shader = self.shaders.get(pip.shader)?;
+
+        let vertex_buffers = pip.layout.iter().flatten().map(|attribute| attribute.buffer_index + 1).max();
+        bindings.check_counts(shader.images.len(), vertex_buffers.unwrap_or(0))?;
+        if let Some(index_buffer) = bindings.index_buffer {
+            if self.buffers.get(index_buffer)?.buffer_type != BufferType::IndexBuffer {
+                return Err(GfxError::LayoutMismatch(
+                    "Bindings::index_buffer should be created with BufferType::IndexBuffer".to_string(),
+                ));
+            }
+        }
 
         for (n, shader_image) in shader.images.iter().enumerate() {
-            let bindings_image = bindings
-                .images
-                .get(n)
-                .unwrap_or_else(|| panic!("Image count in bindings and shader did not match!"));
+            let bindings_image = &bindings.images[n];
             if shader_image.gl_loc.is_some() {
                 unsafe {
-                    self.cache.bind_texture(&self.gl, n, self.textures[bindings_image.0].raw);
+                    let texture = self.textures.get(*bindings_image)?;
+                    self.cache.bind_texture(&self.gl, n, texture.target(), texture.raw);
                     self.gl.uniform_1_i32(shader_image.gl_loc.as_ref(), n as i32);
                 }
             }
         }
 
-        let pip = &self.pipelines[self.cache.cur_pipeline.unwrap().0];
+        if let Some(index_buffer) = bindings.index_buffer {
+            let ib = *self.buffers.get(index_buffer)?;
+            self.cache
+                .bind_buffer(&self.gl, glow::ELEMENT_ARRAY_BUFFER, ib.gl_buf, ib.index_type);
+        }
+
+        let pip = &self.pipelines[self.cache.cur_pipeline.unwrap()];
 
-        for attr_index in 0..MAX_VERTEX_ATTRIBUTES {
+        for attr_index in 0..self.cache.attributes.len() {
             let cached_attr = &mut self.cache.attributes[attr_index];
 
             let pip_attribute = pip.layout.get(attr_index).copied();
 
             if let Some(Some(attribute)) = pip_attribute {
                 let vb = bindings.vertex_buffers[attribute.buffer_index];
-                let vb = self.buffers[vb.0];
+                let vb = *self.buffers.get(vb)?;
 
                 if cached_attr.map_or(true, |cached_attr| {
                     attribute != cached_attr.attribute || cached_attr.gl_vbuf != vb.gl_buf
@@ -459,27 +821,22 @@ impl QuadContext {
                 *cached_attr = None;
             }
         }
-    }
-
-    pub fn apply_uniforms(&mut self, uniforms: UniformsSource) {
-        self.apply_uniforms_from_bytes(uniforms.0.as_slice(), uniforms.0.size)
+        Ok(())
     }
 
     #[rustfmt::skip]
-    pub fn apply_uniforms_from_bytes(&mut self, uniforms: &[u8], size: usize) {
-        let pip = &self.pipelines[self.cache.cur_pipeline.unwrap().0];
-        let shader = &self.shaders[pip.shader.0];
+    fn apply_uniforms_from_bytes(&mut self, uniforms: &[u8], size: usize) -> Result<(), GfxError> {
+        let pip = self.pipelines.get(self.cache.cur_pipeline.ok_or(GfxError::NoPipeline)?)?;
+        let shader = self.shaders.get(pip.shader)?;
+
+        let expected = shader.uniforms.iter().map(|uniform| uniform.uniform_type.size() * uniform.array_count as usize).sum();
+        check_uniforms_size(expected, size.min(uniforms.len()))?;
 
         let mut offset = 0;
 
         for (_, uniform) in shader.uniforms.iter().enumerate() {
             use UniformType::*;
 
-            assert!(
-                offset <= size - uniform.uniform_type.size() / 4,
-                "Uniforms struct does not match shader uniforms layout"
-            );
-
             unsafe {
                 let f = bytemuck::cast_slice(&uniforms[offset..]);
                 let i = bytemuck::cast_slice(&uniforms[offset..]);
@@ -500,10 +857,13 @@ impl QuadContext {
             }
             offset += uniform.uniform_type.size() * uniform.array_count as usize;
         }
+        self.cache.stats.uniform_uploads += 1;
+        Ok(())
     }
 
-    pub fn clear(&mut self, color: Option<Color>, depth: Option<f32>, stencil: Option<i32>) {
+    fn clear(&mut self, color: Option<Color>, depth: Option<f32>, stencil: Option<i32>) {
         let mut bits = 0;
+        let depth_write = self.cache.depth_write;
         unsafe {
             if let Some(c) = color {
                 bits |= glow::COLOR_BUFFER_BIT;
@@ -513,7 +873,9 @@ impl QuadContext {
 
             if let Some(v) = depth {
                 bits |= glow::DEPTH_BUFFER_BIT;
-                self.gl.clear_depth_f32(v)
+                self.gl.clear_depth_f32(v);
+                // glClear obeys the depth mask, a pipeline without depth writes would keep the old depth
+                self.set_depth_write(true);
             }
 
             if let Some(v) = stencil {
@@ -524,60 +886,163 @@ impl QuadContext {
             if bits != 0 {
                 self.gl.clear(bits)
             }
+            // the draws after the clear still use the mask of the current pipeline
+            self.set_depth_write(depth_write);
         }
     }
 
-    /// start rendering to the default frame buffer
-    pub fn begin_default_pass(&mut self, action: PassAction) {
+    fn clear_attachment(&mut self, index: usize, value: ClearValue) -> Result<(), GfxError> {
+        self.check_clear_attachment(self.cur_pass, index, value)?;
+        unsafe { self.clear_buffer(index, value) }
+        Ok(())
+    }
+
+    fn begin_default_pass_with_store(&mut self, action: PassAction, store: StoreActions) {
+        self.cur_pass = None;
+        self.cur_store = store;
         unsafe {
             self.gl.bind_framebuffer(glow::FRAMEBUFFER, self.default_framebuffer);
             self.gl.viewport(0, 0, self.width, self.height);
             self.gl.scissor(0, 0, self.width, self.height);
         }
-        match action {
-            PassAction::Nothing => {}
-            PassAction::Clear { color, depth, stencil } => {
-                self.clear(color, depth, stencil);
-            }
-        }
+        // the window has a normalized color buffer, integer clear values leave it as it is
+        let action = match action {
+            PassAction::Clear { color, depth, stencil } => PassAction::Clear {
+                color: color.filter(|value| check_clear_value(&[TextureFormat::RGBA8], 0, *value).is_ok()),
+                depth,
+                stencil,
+            },
+            PassAction::Nothing => PassAction::Nothing,
+        };
+        self.apply_pass_action(action, 1);
     }
 
-    /// start rendering to an offscreen framebuffer
-    pub fn begin_pass(&mut self, pass: RenderPass, action: PassAction) {
-        let pass = &self.passes[pass.0];
-        let (framebuffer, w, h) = (
-            pass.gl_fb,
-            self.textures[pass.texture.0].params.width as i32,
-            self.textures[pass.texture.0].params.height as i32,
-        );
+    fn begin_pass_with_store(&mut self, pass: RenderPass, action: PassAction, store: StoreActions) -> Result<(), GfxError> {
+        let descs = self.check_begin_pass(pass, action)?;
+        let (framebuffer, w, h) = (Some(self.passes[pass].gl_fb), descs[0].width as i32, descs[0].height as i32);
+        self.cur_pass = Some(pass);
+        self.cur_store = store;
 
         unsafe {
             self.gl.bind_framebuffer(glow::FRAMEBUFFER, framebuffer);
             self.gl.viewport(0, 0, w, h);
             self.gl.scissor(0, 0, w, h);
         }
-        match action {
-            PassAction::Nothing => {}
-            PassAction::Clear { color, depth, stencil } => {
-                self.clear(color, depth, stencil);
-            }
-        }
+        self.apply_pass_action(action, descs.len());
+        Ok(())
     }
 
-    pub fn end_render_pass(&mut self) {
+    fn end_render_pass(&mut self) {
         unsafe {
+            self.pop_debug_group();
+            let store = std::mem::take(&mut self.cur_store);
+            let invalidate = self.features.framebuffer_invalidation;
+            match self.cur_pass.take() {
+                Some(pass) => {
+                    if let Ok(pass) = self.passes.get(pass) {
+                        // attachments deleted during the pass are left as they are
+                        let mut attachments = pass.color_attachments.iter().chain(&pass.depth_attachment);
+                        if let Ok(descs) = attachments
+                            .map(|attachment| self.attachment_desc(*attachment))
+                            .collect::<Result<Vec<_>, _>>()
+                        {
+                            pass.finish(&self.gl, descs[0].width as i32, descs[0].height as i32, store, invalidate);
+                        }
+                    }
+                }
+                None if invalidate => self.invalidate_default_framebuffer(store),
+                None => {}
+            }
             self.gl.bind_framebuffer(glow::FRAMEBUFFER, self.default_framebuffer);
             self.cache.bind_buffer(&self.gl, glow::ARRAY_BUFFER, None, None);
             self.cache.bind_buffer(&self.gl, glow::ELEMENT_ARRAY_BUFFER, None, None);
         }
     }
 
-    pub fn commit_frame(&mut self) {
+    fn commit_frame(&mut self) {
+        unsafe { self.pop_debug_group() }
         self.cache.clear_buffer_bindings(&self.gl);
         self.cache.clear_texture_bindings(&self.gl);
+        self.frame_stats = std::mem::take(&mut self.cache.stats);
+        self.timers.poll(&self.gl);
+    }
+
+    fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    fn begin_gpu_scope(&mut self, name: &str) {
+        self.timers.begin(&self.gl, name);
+    }
+
+    fn end_gpu_scope(&mut self) {
+        self.timers.end(&self.gl);
+    }
+
+    fn gpu_timings(&self) -> Vec<GpuTiming> {
+        self.timers.timings()
+    }
+
+    fn new_query(&mut self, query_type: QueryType) -> Result<QueryId, GfxError> {
+        self.features.check_query(query_type)?;
+        let raw = unsafe { self.gl.create_query() }.map_err(|message| GfxError::ObjectCreation {
+            kind: ResourceKind::Query,
+            message,
+        })?;
+        Ok(self.queries.insert(QueryInternal {
+            raw,
+            query_type,
+            begun: false,
+            label: None,
+        }))
+    }
+
+    fn delete_query(&mut self, query: QueryId) -> Result<(), GfxError> {
+        let internal = self.queries.remove(query)?;
+        if self.active_queries.contains(query) {
+            self.active_queries.end(query)?;
+            unsafe { self.gl.end_query(internal.query_type as u32) }
+        }
+        unsafe { self.gl.delete_query(internal.raw) }
+        Ok(())
+    }
+
+    fn begin_query(&mut self, query: QueryId) -> Result<(), GfxError> {
+        let internal = self.queries.get_mut(query)?;
+        self.active_queries.begin(internal.query_type, query)?;
+        unsafe { self.gl.begin_query(internal.query_type as u32, internal.raw) }
+
+        if !internal.begun {
+            internal.begun = true;
+            if let Some(label) = internal.label.clone() {
+                if self.gl.supports_debug() {
+                    unsafe { self.label_object(Resource::Query(query), Some(&label)) }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn end_query(&mut self, query: QueryId) -> Result<(), GfxError> {
+        let query_type = self.queries.get(query)?.query_type;
+        self.active_queries.end(query)?;
+        unsafe { self.gl.end_query(query_type as u32) }
+        Ok(())
     }
 
-    pub fn draw(&self, first: i32, count: i32, instance_count: i32) {
+    fn query_result(&mut self, query: QueryId) -> Result<Option<u64>, GfxError> {
+        let internal = self.queries.get(query)?;
+        self.active_queries.check_ended(query, internal.begun)?;
+
+        unsafe {
+            if self.gl.get_query_parameter_u32(internal.raw, glow::QUERY_RESULT_AVAILABLE) == 0 {
+                return Ok(None);
+            }
+            Ok(Some(self.gl.get_query_parameter_u32(internal.raw, glow::QUERY_RESULT) as u64))
+        }
+    }
+
+    fn draw(&mut self, first: i32, count: i32, instance_count: i32) {
         assert!(self.cache.cur_pipeline.is_some(), "Drawing without any binded pipeline");
 
         if !self.features.instancing && instance_count != 1 {
@@ -586,6 +1051,41 @@ impl QuadContext {
             return;
         }
 
-        unsafe { self.gl.draw_arrays_instanced(glow::TRIANGLES, first, count, instance_count) }
+        let primitive_type = self.pipelines[self.cache.cur_pipeline.unwrap()].params.primitive_type;
+        self.cache.stats.count_draw(count, instance_count);
+
+        unsafe { self.gl.draw_arrays_instanced(primitive_type as u32, first, count, instance_count) }
+    }
+
+    fn draw_indexed(&mut self, first_index: i32, count: i32, instance_count: i32) {
+        assert!(self.cache.cur_pipeline.is_some(), "Drawing without any binded pipeline");
+
+        let index_type = self.cache.index_type.expect("Indexed drawing without any binded index buffer");
+
+        if !self.features.instancing && instance_count != 1 {
+            eprintln!("Instanced rendering is not supported by the GPU");
+            eprintln!("Ignoring this draw call");
+            return;
+        }
+
+        let gl_index_type = match index_type {
+            1 => glow::UNSIGNED_BYTE,
+            2 => glow::UNSIGNED_SHORT,
+            4 => glow::UNSIGNED_INT,
+            _ => unreachable!(),
+        };
+
+        let primitive_type = self.pipelines[self.cache.cur_pipeline.unwrap()].params.primitive_type;
+        self.cache.stats.count_draw(count, instance_count);
+
+        unsafe {
+            self.gl.draw_elements_instanced(
+                primitive_type as u32,
+                count,
+                gl_index_type,
+                first_index * index_type as i32,
+                instance_count,
+            )
+        }
     }
 }
@@ -1,7 +1,12 @@
 use super::*;
+use crate::error::{GfxError, ResourceKind};
+use crate::resource::{resource_id, Handle};
+use crate::state::GlBackend;
+use std::ops::Range;
 
-#[derive(Clone, Debug, Copy, PartialEq)]
-pub struct TextureId(pub usize);
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub struct TextureId(pub(crate) Handle);
+resource_id!(TextureId, Texture);
 
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct Texture {
@@ -10,42 +15,34 @@ pub(crate) struct Texture {
 }
 
 impl Texture {
-    pub fn new(ctx: &mut QuadContext, _access: TextureAccess, bytes: Option<&[u8]>, params: TextureParams) -> Texture {
-        if let Some(bytes_data) = bytes {
-            assert_eq!(params.format.size(params.width, params.height) as usize, bytes_data.len());
+    pub fn new(ctx: &mut GlBackend, _access: TextureAccess, bytes: Option<&[u8]>, params: TextureParams) -> Result<Texture, GfxError> {
+        params.check_levels()?;
+        if let Some(bytes) = bytes {
+            params.check_size(params.width, params.height, bytes)?;
         }
 
-        let (internal_format, format, pixel_type) = params.format.into();
+        let target = params.kind.target();
+        let texture = unsafe { ctx.gl.create_texture() }.map_err(|message| GfxError::ObjectCreation {
+            kind: ResourceKind::Texture,
+            message,
+        })?;
+        let texture = Some(texture);
 
         ctx.cache.store_texture_binding(0);
 
-        let texture;
-
         unsafe {
-            texture = ctx.gl.create_texture().ok();
-            ctx.cache.bind_texture(&ctx.gl, 0, texture);
+            ctx.cache.bind_texture(&ctx.gl, 0, target, texture);
             ctx.gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
 
             if cfg!(not(target_arch = "wasm32")) {
                 if params.format == TextureFormat::Alpha {
-                    ctx.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_SWIZZLE_A, glow::RED as _);
+                    ctx.gl.tex_parameter_i32(target, glow::TEXTURE_SWIZZLE_A, glow::RED as _);
                 } else {
-                    ctx.gl
-                        .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_SWIZZLE_A, glow::ALPHA as _);
+                    ctx.gl.tex_parameter_i32(target, glow::TEXTURE_SWIZZLE_A, glow::ALPHA as _);
                 }
             }
 
-            ctx.gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                internal_format as i32,
-                params.width as i32,
-                params.height as i32,
-                0,
-                format,
-                pixel_type,
-                bytes,
-            );
+            allocate(&ctx.gl, params, bytes);
 
             let wrap = match params.wrap {
                 TextureWrap::Repeat => glow::REPEAT,
@@ -58,34 +55,38 @@ impl Texture {
                 FilterMode::Linear => glow::LINEAR,
             };
 
-            ctx.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, wrap as i32);
-            ctx.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, wrap as i32);
-            ctx.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, filter as i32);
-            ctx.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, filter as i32);
+            ctx.gl.tex_parameter_i32(target, glow::TEXTURE_WRAP_S, wrap as i32);
+            ctx.gl.tex_parameter_i32(target, glow::TEXTURE_WRAP_T, wrap as i32);
+            ctx.gl.tex_parameter_i32(target, glow::TEXTURE_MIN_FILTER, filter as i32);
+            ctx.gl.tex_parameter_i32(target, glow::TEXTURE_MAG_FILTER, filter as i32);
         }
         ctx.cache.restore_texture_binding(&ctx.gl, 0);
 
-        Texture { raw: texture, params }
+        Ok(Texture { raw: texture, params })
+    }
+
+    pub fn target(&self) -> u32 {
+        self.params.kind.target()
     }
 
-    pub fn set_filter(&self, ctx: &mut QuadContext, filter: FilterMode) {
+    pub fn set_filter(&self, ctx: &mut GlBackend, filter: FilterMode) {
         ctx.cache.store_texture_binding(0);
-        ctx.cache.bind_texture(&ctx.gl, 0, self.raw);
+        ctx.cache.bind_texture(&ctx.gl, 0, self.target(), self.raw);
 
         let filter = match filter {
             FilterMode::Nearest => glow::NEAREST,
             FilterMode::Linear => glow::LINEAR,
         };
         unsafe {
-            ctx.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, filter as i32);
-            ctx.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, filter as i32);
+            ctx.gl.tex_parameter_i32(self.target(), glow::TEXTURE_MIN_FILTER, filter as i32);
+            ctx.gl.tex_parameter_i32(self.target(), glow::TEXTURE_MAG_FILTER, filter as i32);
         }
         ctx.cache.restore_texture_binding(&ctx.gl, 0);
     }
 
-    pub fn set_wrap(&self, ctx: &mut QuadContext, wrap: TextureWrap) {
+    pub fn set_wrap(&self, ctx: &mut GlBackend, wrap: TextureWrap) {
         ctx.cache.store_texture_binding(0);
-        ctx.cache.bind_texture(&ctx.gl, 0, self.raw);
+        ctx.cache.bind_texture(&ctx.gl, 0, self.target(), self.raw);
         let wrap = match wrap {
             TextureWrap::Repeat => glow::REPEAT,
             TextureWrap::Mirror => glow::MIRRORED_REPEAT,
@@ -93,45 +94,46 @@ impl Texture {
         };
 
         unsafe {
-            ctx.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, wrap as i32);
-            ctx.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, wrap as i32);
+            ctx.gl.tex_parameter_i32(self.target(), glow::TEXTURE_WRAP_S, wrap as i32);
+            ctx.gl.tex_parameter_i32(self.target(), glow::TEXTURE_WRAP_T, wrap as i32);
         }
         ctx.cache.restore_texture_binding(&ctx.gl, 0);
     }
 
-    pub fn resize(&mut self, ctx: &mut QuadContext, width: u32, height: u32, bytes: Option<&[u8]>) {
-        ctx.cache.store_texture_binding(0);
-        ctx.cache.bind_texture(&ctx.gl, 0, self.raw);
-
-        let (internal_format, format, pixel_type) = self.params.format.into();
+    pub fn resize(&mut self, ctx: &mut GlBackend, width: u32, height: u32, bytes: Option<&[u8]>) -> Result<(), GfxError> {
+        let params = TextureParams {
+            width,
+            height,
+            ..self.params
+        };
+        params.check_levels()?;
+        if let Some(bytes) = bytes {
+            params.check_size(width, height, bytes)?;
+        }
 
-        self.params.width = width;
-        self.params.height = height;
+        ctx.cache.store_texture_binding(0);
+        ctx.cache.bind_texture(&ctx.gl, 0, self.target(), self.raw);
 
-        unsafe {
-            ctx.gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                internal_format as i32,
-                self.params.width as i32,
-                self.params.height as i32,
-                0,
-                format,
-                pixel_type,
-                bytes,
-            );
-        }
+        self.params = params;
+        unsafe { allocate(&ctx.gl, self.params, bytes) }
 
         ctx.cache.restore_texture_binding(&ctx.gl, 0);
+        Ok(())
     }
 
-    pub fn update_texture_part(&self, ctx: &mut QuadContext, x_offset: i32, y_offset: i32, width: i32, height: i32, bytes: &[u8]) {
-        assert_eq!(self.size(width as _, height as _), bytes.len());
-        assert!(x_offset + width <= self.params.width as _);
-        assert!(y_offset + height <= self.params.height as _);
+    pub fn update_texture_part(
+        &self,
+        ctx: &mut GlBackend,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        bytes: &[u8],
+    ) -> Result<(), GfxError> {
+        self.params.check_region(x_offset, y_offset, width, height, bytes)?;
 
         ctx.cache.store_texture_binding(0);
-        ctx.cache.bind_texture(&ctx.gl, 0, self.raw);
+        ctx.cache.bind_texture(&ctx.gl, 0, self.target(), self.raw);
 
         let (_, format, pixel_type) = self.params.format.into();
 
@@ -140,39 +142,59 @@ impl Texture {
 
             if cfg!(not(target_arch = "wasm32")) {
                 if self.params.format == TextureFormat::Alpha {
-                    ctx.gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_SWIZZLE_A, glow::RED as _);
+                    ctx.gl.tex_parameter_i32(self.target(), glow::TEXTURE_SWIZZLE_A, glow::RED as _);
                 } else {
-                    ctx.gl
-                        .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_SWIZZLE_A, glow::ALPHA as _);
+                    ctx.gl.tex_parameter_i32(self.target(), glow::TEXTURE_SWIZZLE_A, glow::ALPHA as _);
                 }
             }
 
-            ctx.gl.tex_sub_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                x_offset as _,
-                y_offset as _,
-                width as _,
-                height as _,
-                format,
-                pixel_type,
-                glow::PixelUnpackData::Slice(bytes),
-            );
+            let pixels = glow::PixelUnpackData::Slice(bytes);
+            match self.params.kind {
+                TextureKind::Texture2D | TextureKind::CubeMap => ctx.gl.tex_sub_image_2d(
+                    image_target(self.params.kind, 0),
+                    0,
+                    x_offset as _,
+                    y_offset as _,
+                    width as _,
+                    height as _,
+                    format,
+                    pixel_type,
+                    pixels,
+                ),
+                TextureKind::Array(_) => ctx.gl.tex_sub_image_3d(
+                    glow::TEXTURE_2D_ARRAY,
+                    0,
+                    x_offset as _,
+                    y_offset as _,
+                    0,
+                    width as _,
+                    height as _,
+                    1,
+                    format,
+                    pixel_type,
+                    pixels,
+                ),
+            }
         }
 
         ctx.cache.restore_texture_binding(&ctx.gl, 0);
+        Ok(())
     }
 
     /// Read texture data into CPU memory
-    pub fn read_pixels(&self, ctx: &QuadContext, bytes: &mut [u8]) {
+    pub fn read_pixels(&self, ctx: &GlBackend, bytes: &mut [u8]) -> Result<(), GfxError> {
+        self.params.check_read(bytes)?;
+
         let (_, format, pixel_type) = self.params.format.into();
         unsafe {
             let binded_fbo = convert_framebuffer(ctx.gl.get_parameter_i32(glow::DRAW_FRAMEBUFFER_BINDING));
 
-            let fbo = ctx.gl.create_framebuffer().ok();
-            ctx.gl.bind_framebuffer(glow::FRAMEBUFFER, fbo);
-            ctx.gl
-                .framebuffer_texture_2d(glow::FRAMEBUFFER, glow::COLOR_ATTACHMENT0, glow::TEXTURE_2D, self.raw, 0);
+            let fbo = ctx.gl.create_framebuffer().map_err(|message| GfxError::ObjectCreation {
+                kind: ResourceKind::Texture,
+                message,
+            })?;
+            ctx.gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+            self.attach(&ctx.gl, glow::COLOR_ATTACHMENT0, 0, 0);
 
             ctx.gl.read_pixels(
                 0,
@@ -185,13 +207,72 @@ impl Texture {
             );
 
             ctx.gl.bind_framebuffer(glow::FRAMEBUFFER, binded_fbo);
-            ctx.gl.delete_framebuffer(fbo.unwrap());
+            ctx.gl.delete_framebuffer(fbo);
+        }
+        Ok(())
+    }
+
+    /// Attach mip `level` of face or layer `layer` to the framebuffer bound to `GL_FRAMEBUFFER`
+    pub unsafe fn attach(&self, gl: &glow::Context, attachment_point: u32, level: u32, layer: u32) {
+        match self.params.kind {
+            TextureKind::Texture2D | TextureKind::CubeMap => gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                attachment_point,
+                image_target(self.params.kind, layer),
+                self.raw,
+                level as i32,
+            ),
+            TextureKind::Array(_) => {
+                gl.framebuffer_texture_layer(glow::FRAMEBUFFER, attachment_point, self.raw, level as i32, layer as i32)
+            }
         }
     }
+}
 
-    #[inline]
-    fn size(&self, width: u32, height: u32) -> usize {
-        self.params.format.size(width, height) as usize
+/// Target of face `face` of a cube map, `GL_TEXTURE_2D` for the other 2D textures
+fn image_target(kind: TextureKind, face: u32) -> u32 {
+    match kind {
+        TextureKind::CubeMap => glow::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+        _ => glow::TEXTURE_2D,
+    }
+}
+
+/// Allocate every mip level of the texture bound to texture unit 0, `bytes` hold level 0 of each face or layer
+unsafe fn allocate(gl: &glow::Context, params: TextureParams, bytes: Option<&[u8]>) {
+    let (internal_format, format, pixel_type) = params.format.into();
+    for level in 0..params.levels {
+        let (width, height) = params.level_size(level);
+        let bytes = bytes.filter(|_| level == 0);
+        match params.kind {
+            TextureKind::Texture2D | TextureKind::CubeMap => {
+                let image_size = params.image_size(level);
+                for face in 0..params.layers() {
+                    gl.tex_image_2d(
+                        image_target(params.kind, face),
+                        level as i32,
+                        internal_format as i32,
+                        width as i32,
+                        height as i32,
+                        0,
+                        format,
+                        pixel_type,
+                        bytes.map(|bytes| &bytes[face as usize * image_size..][..image_size]),
+                    );
+                }
+            }
+            TextureKind::Array(layers) => gl.tex_image_3d(
+                glow::TEXTURE_2D_ARRAY,
+                level as i32,
+                internal_format as i32,
+                width as i32,
+                height as i32,
+                layers as i32,
+                0,
+                format,
+                pixel_type,
+                bytes,
+            ),
+        }
     }
 }
 
@@ -202,18 +283,53 @@ impl Texture {
 pub enum TextureFormat {
     RGB8,
     RGBA8,
+    /// 16 bit depth
     Depth,
     Alpha,
+    /// 24 bit depth and 8 bit stencil, packed into a native endian u32 with the depth in the high bits.
+    /// Attached to a render pass, it enables the stencil test of the pipelines.
+    Depth24Stencil8,
+    /// 32 bit float per channel, for HDR render targets that hold values outside of 0..1.
+    /// Rendering to it needs `EXT_color_buffer_float` on GLES and WebGL.
+    RGBA32F,
+    /// Single 32 bit signed integer channel. Integer textures are not filterable, use `FilterMode::Nearest`.
+    R32I,
+    /// Single 32 bit unsigned integer channel. Integer textures are not filterable, use `FilterMode::Nearest`.
+    R32UI,
 }
 impl TextureFormat {
-    /// Returns the size in bytes of texture with `dimensions`.
-    pub fn size(self, width: u32, height: u32) -> u32 {
-        let square = width * height;
+    /// The format can be the depth attachment of a render pass
+    pub fn has_depth(self) -> bool {
+        matches!(self, TextureFormat::Depth | TextureFormat::Depth24Stencil8)
+    }
+
+    pub fn has_stencil(self) -> bool {
+        self == TextureFormat::Depth24Stencil8
+    }
+
+    /// Shaders read and write the format as integers, blending does not apply to it
+    pub fn is_integer(self) -> bool {
+        matches!(self, TextureFormat::R32I | TextureFormat::R32UI)
+    }
+
+    /// Returns the size in bytes of texture with `dimensions`, `OutOfBounds` when it does not fit in memory.
+    pub fn size(self, width: u32, height: u32) -> Result<usize, GfxError> {
+        (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|square| square.checked_mul(self.pixel_size()))
+            .ok_or(GfxError::OutOfBounds(ResourceKind::Texture))
+    }
+
+    /// Size in bytes of one pixel
+    pub(crate) fn pixel_size(self) -> usize {
         match self {
-            TextureFormat::RGB8 => 3 * square,
-            TextureFormat::RGBA8 => 4 * square,
-            TextureFormat::Depth => 2 * square,
-            TextureFormat::Alpha => square,
+            TextureFormat::RGB8 => 3,
+            TextureFormat::RGBA8 => 4,
+            TextureFormat::Depth => 2,
+            TextureFormat::Alpha => 1,
+            TextureFormat::Depth24Stencil8 => 4,
+            TextureFormat::RGBA32F => 16,
+            TextureFormat::R32I | TextureFormat::R32UI => 4,
         }
     }
 }
@@ -225,6 +341,10 @@ impl From<TextureFormat> for (u32, u32, u32) {
             TextureFormat::RGB8 => (glow::RGB, glow::RGB, glow::UNSIGNED_BYTE),
             TextureFormat::RGBA8 => (glow::RGBA, glow::RGBA, glow::UNSIGNED_BYTE),
             TextureFormat::Depth => (glow::DEPTH_COMPONENT, glow::DEPTH_COMPONENT, glow::UNSIGNED_SHORT),
+            TextureFormat::Depth24Stencil8 => (glow::DEPTH24_STENCIL8, glow::DEPTH_STENCIL, glow::UNSIGNED_INT_24_8),
+            TextureFormat::RGBA32F => (glow::RGBA32F, glow::RGBA, glow::FLOAT),
+            TextureFormat::R32I => (glow::R32I, glow::RED_INTEGER, glow::INT),
+            TextureFormat::R32UI => (glow::R32UI, glow::RED_INTEGER, glow::UNSIGNED_INT),
             #[cfg(target_arch = "wasm32")]
             TextureFormat::Alpha => (glow::ALPHA, glow::ALPHA, glow::UNSIGNED_BYTE),
             #[cfg(not(target_arch = "wasm32"))]
@@ -250,6 +370,30 @@ pub enum FilterMode {
     Nearest,
 }
 
+/// Images of a texture in each mip level.
+/// `texture_update_part` and `texture_read_pixels` access level 0 of the first face or layer.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum TextureKind {
+    /// A single image, sampled with `sampler2D`
+    #[default]
+    Texture2D,
+    /// Six square faces in the order +X, -X, +Y, -Y, +Z, -Z, sampled with `samplerCube`
+    CubeMap,
+    /// Layers of the same size, sampled with `sampler2DArray`
+    Array(u32),
+}
+
+impl TextureKind {
+    /// Target the texture is bound to
+    pub(crate) fn target(self) -> u32 {
+        match self {
+            TextureKind::Texture2D => glow::TEXTURE_2D,
+            TextureKind::CubeMap => glow::TEXTURE_CUBE_MAP,
+            TextureKind::Array(_) => glow::TEXTURE_2D_ARRAY,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum TextureAccess {
     /// Used as read-only from GPU
@@ -258,14 +402,123 @@ pub enum TextureAccess {
     RenderTarget,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct TextureParams {
     pub format: TextureFormat,
     pub wrap: TextureWrap,
     pub filter: FilterMode,
     pub width: u32,
     pub height: u32,
+    pub kind: TextureKind,
+    /// Mip levels, each half the size of the one before down to 1x1. 1 for a texture without mipmaps.
+    /// Levels past 0 are left undefined by `new_texture`, they are meant to be rendered into.
+    pub levels: u32,
 }
+impl TextureParams {
+    /// Faces of a cube map, layers of an array texture, 1 for a 2D texture
+    pub fn layers(&self) -> u32 {
+        match self.kind {
+            TextureKind::Texture2D => 1,
+            TextureKind::CubeMap => 6,
+            TextureKind::Array(layers) => layers,
+        }
+    }
+
+    /// Width and height of mip `level`
+    pub fn level_size(&self, level: u32) -> (u32, u32) {
+        let size = |size: u32| size.checked_shr(level).unwrap_or(0).max(1);
+        (size(self.width), size(self.height))
+    }
+
+    /// Check that the texture has at least one mip level and layer, no levels past 1x1, square cube map faces
+    /// and that all of its storage fits in memory
+    pub(crate) fn check_levels(&self) -> Result<(), GfxError> {
+        let max_levels = u32::BITS - self.width.max(self.height).max(1).leading_zeros();
+        if self.levels == 0 || self.levels > max_levels {
+            return Err(GfxError::LayoutMismatch(format!(
+                "{} mip levels of a {}x{} texture, it has 1 to {max_levels}",
+                self.levels, self.width, self.height
+            )));
+        }
+        match self.kind {
+            TextureKind::CubeMap if self.width != self.height => Err(GfxError::LayoutMismatch(format!(
+                "{}x{} cube map, its faces are square",
+                self.width, self.height
+            ))),
+            TextureKind::Array(0) => Err(GfxError::LayoutMismatch("array texture without layers".to_string())),
+            _ => self.storage_size().map(|_| ()),
+        }
+    }
+
+    /// Size in bytes of every mip level of every face or layer
+    fn storage_size(&self) -> Result<usize, GfxError> {
+        (0..self.levels)
+            .try_fold(0usize, |size, level| {
+                let (width, height) = self.level_size(level);
+                size.checked_add(self.format.size(width, height)?)
+                    .ok_or(GfxError::OutOfBounds(ResourceKind::Texture))
+            })?
+            .checked_mul(self.layers() as usize)
+            .ok_or(GfxError::OutOfBounds(ResourceKind::Texture))
+    }
+
+    /// Size in bytes of a face or layer of mip `level`, the storage was checked to fit by `check_levels`
+    pub(crate) fn image_size(&self, level: u32) -> usize {
+        let (width, height) = self.level_size(level);
+        width as usize * height as usize * self.format.pixel_size()
+    }
+
+    /// Zeroed storage of every mip level of every face or layer for the CPU backends, starting with `bytes`
+    pub(crate) fn storage(&self, bytes: Option<&[u8]>) -> Vec<u8> {
+        let size = (0..self.levels).map(|level| self.image_size(level)).sum::<usize>() * self.layers() as usize;
+        let mut storage = vec![0; size];
+        if let Some(bytes) = bytes {
+            storage[..bytes.len()].copy_from_slice(bytes);
+        }
+        storage
+    }
+
+    /// Bytes of face or layer `layer` of mip `level` in the storage of the CPU backends,
+    /// the levels one after the other with all of their faces or layers
+    pub(crate) fn image_range(&self, level: u32, layer: u32) -> Range<usize> {
+        let start = (0..level).map(|level| self.image_size(level)).sum::<usize>() * self.layers() as usize
+            + layer as usize * self.image_size(level);
+        start..start + self.image_size(level)
+    }
+
+    /// Check that `bytes` hold a whole `width` x `height` image of this format for each face or layer
+    pub(crate) fn check_size(&self, width: u32, height: u32, bytes: &[u8]) -> Result<(), GfxError> {
+        let size = self.format.size(width, height)?.checked_mul(self.layers() as usize);
+        check_len(size.ok_or(GfxError::OutOfBounds(ResourceKind::Texture))?, bytes)
+    }
+
+    /// Check that the region fits into the texture and `bytes` hold all of it
+    pub(crate) fn check_region(&self, x_offset: i32, y_offset: i32, width: i32, height: i32, bytes: &[u8]) -> Result<(), GfxError> {
+        if x_offset < 0
+            || y_offset < 0
+            || width < 0
+            || height < 0
+            || x_offset + width > self.width as i32
+            || y_offset + height > self.height as i32
+        {
+            return Err(GfxError::OutOfBounds(ResourceKind::Texture));
+        }
+        check_len(self.format.size(width as _, height as _)?, bytes)
+    }
+
+    /// Check that `bytes` are large enough to read the whole texture into
+    pub(crate) fn check_read(&self, bytes: &[u8]) -> Result<(), GfxError> {
+        let expected = self.format.size(self.width, self.height)?;
+        if bytes.len() < expected {
+            return Err(GfxError::SizeMismatch {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
 impl Default for TextureParams {
     fn default() -> Self {
         TextureParams {
@@ -274,6 +527,18 @@ impl Default for TextureParams {
             filter: FilterMode::Linear,
             width: 0,
             height: 0,
+            kind: TextureKind::Texture2D,
+            levels: 1,
         }
     }
 }
+
+fn check_len(expected: usize, bytes: &[u8]) -> Result<(), GfxError> {
+    if bytes.len() != expected {
+        return Err(GfxError::SizeMismatch {
+            expected,
+            actual: bytes.len(),
+        });
+    }
+    Ok(())
+}
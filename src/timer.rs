@@ -0,0 +1,123 @@
+use crate::backend::GpuTiming;
+use glow::HasContext;
+use std::{collections::VecDeque, time::Duration};
+
+/// `GL_GPU_DISJOINT_EXT` of `EXT_disjoint_timer_query`
+const GPU_DISJOINT: u32 = 0x8FBB;
+/// Number of samples in the rolling average of a scope
+const AVERAGE_WINDOW: usize = 32;
+
+struct Scope {
+    name: String,
+    samples: VecDeque<Duration>,
+}
+
+/// `GL_TIME_ELAPSED` queries of the named GPU scopes.
+///
+/// Results are polled without blocking on `commit_frame`, so they arrive a few frames late.
+pub(crate) struct GpuTimers {
+    pub supported: bool,
+    /// `EXT_disjoint_timer_query` reports through `GL_GPU_DISJOINT_EXT` when timings became meaningless,
+    /// reading it without the extension is an `INVALID_ENUM` error
+    check_disjoint: bool,
+    scopes: Vec<Scope>,
+    active: Option<(usize, glow::Query)>,
+    /// Ended queries waiting for the results, in the order they were issued
+    pending: VecDeque<(usize, glow::Query)>,
+    free: Vec<glow::Query>,
+}
+
+impl GpuTimers {
+    pub fn new(gl: &glow::Context) -> Self {
+        let version = gl.version();
+        let extensions = gl.supported_extensions();
+        let disjoint_ext = extensions.contains("GL_EXT_disjoint_timer_query")
+            || extensions.contains("EXT_disjoint_timer_query")
+            || extensions.contains("EXT_disjoint_timer_query_webgl2");
+        let core = !version.is_embedded && (version.major, version.minor) >= (3, 3);
+
+        Self {
+            supported: core || extensions.contains("GL_ARB_timer_query") || disjoint_ext,
+            check_disjoint: disjoint_ext,
+            scopes: vec![],
+            active: None,
+            pending: VecDeque::new(),
+            free: vec![],
+        }
+    }
+
+    pub fn begin(&mut self, gl: &glow::Context, name: &str) {
+        if !self.supported {
+            return;
+        }
+        // time elapsed queries can not be nested
+        self.end(gl);
+
+        let query = match self.free.pop() {
+            Some(query) => query,
+            None => match unsafe { gl.create_query() } {
+                Ok(query) => query,
+                Err(_) => return,
+            },
+        };
+        let scope = match self.scopes.iter().position(|scope| scope.name == name) {
+            Some(scope) => scope,
+            None => {
+                self.scopes.push(Scope {
+                    name: name.to_string(),
+                    samples: VecDeque::with_capacity(AVERAGE_WINDOW),
+                });
+                self.scopes.len() - 1
+            }
+        };
+
+        unsafe { gl.begin_query(glow::TIME_ELAPSED, query) }
+        self.active = Some((scope, query));
+    }
+
+    pub fn end(&mut self, gl: &glow::Context) {
+        if let Some(active) = self.active.take() {
+            unsafe { gl.end_query(glow::TIME_ELAPSED) }
+            self.pending.push_back(active);
+        }
+    }
+
+    /// Collect the results that are already available
+    pub fn poll(&mut self, gl: &glow::Context) {
+        if !self.supported {
+            return;
+        }
+        if self.check_disjoint && unsafe { gl.get_parameter_i32(GPU_DISJOINT) } != 0 {
+            self.free.extend(self.pending.drain(..).map(|(_, query)| query));
+            return;
+        }
+
+        while let Some(&(scope, query)) = self.pending.front() {
+            if unsafe { gl.get_query_parameter_u32(query, glow::QUERY_RESULT_AVAILABLE) } == 0 {
+                break;
+            }
+            let nanos = unsafe { gl.get_query_parameter_u32(query, glow::QUERY_RESULT) };
+
+            let samples = &mut self.scopes[scope].samples;
+            if samples.len() == AVERAGE_WINDOW {
+                samples.pop_front();
+            }
+            samples.push_back(Duration::from_nanos(nanos as u64));
+
+            self.pending.pop_front();
+            self.free.push(query);
+        }
+    }
+
+    pub fn timings(&self) -> Vec<GpuTiming> {
+        self.scopes
+            .iter()
+            .filter(|scope| !scope.samples.is_empty())
+            .map(|scope| GpuTiming {
+                name: scope.name.clone(),
+                average: scope.samples.iter().sum::<Duration>() / scope.samples.len() as u32,
+                last: *scope.samples.back().unwrap(),
+            })
+            .collect()
+    }
+}
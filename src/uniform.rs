@@ -1,4 +1,5 @@
 use crate::buffer::Arg;
+use crate::error::GfxError;
 
 #[derive(Clone, Copy, Debug)]
 pub enum UniformType {
@@ -51,6 +52,26 @@ pub struct UniformBlockLayout {
     pub uniforms: Vec<UniformDesc>,
 }
 
+impl UniformBlockLayout {
+    /// Bytes of the uniforms struct the layout describes
+    pub fn size(&self) -> usize {
+        self.uniforms
+            .iter()
+            .map(|uniform| uniform.uniform_type.size() * uniform.array_count)
+            .sum()
+    }
+}
+
+/// Check that `size` bytes of uniforms cover the `expected` bytes a shader takes
+pub(crate) fn check_uniforms_size(expected: usize, size: usize) -> Result<(), GfxError> {
+    if size < expected {
+        return Err(GfxError::LayoutMismatch(format!(
+            "shader uniforms take {expected} bytes, uniforms struct is {size} bytes"
+        )));
+    }
+    Ok(())
+}
+
 impl UniformDesc {
     pub fn new(name: &str, uniform_type: UniformType) -> UniformDesc {
         UniformDesc {